@@ -0,0 +1,159 @@
+//! encrypted_str! and encrypted_bytes!, which encrypt a literal with AES-CTR while the
+//! calling crate compiles and expand to an aes::secret value that decrypts it on first use.
+//! Every call site gets its own key and IV, derived with the aes KBKDF from a build key:
+//! AES_MACRO_KEY as 32 hex digits when it is set, for reproducible builds, and fresh
+//! randomness for each compiler run otherwise, in which case every call site warns that
+//! the build key is random. The call site's file, line, column and
+//! tokens are the context. The expansion carries the derived key, so the plaintext stays
+//! out of the binary's strings and no more than that.
+//!
+//! The literal is encrypted by a const item in the expansion, so the compiler parses and
+//! unescapes it and only the ciphertext reaches the binary. The calling crate depends on
+//! aes as well as on this crate. Cargo does not track AES_MACRO_KEY, so changing it needs
+//! a clean build of that crate to reach the ciphertext.
+
+#![warn(clippy::all)]
+#![allow(clippy::let_with_type_underscore)]
+
+use std::{env, sync::OnceLock};
+
+use aes::{kdf, rng::{EntropySource, OsEntropy}, Block, BLOCK_SIZE};
+use proc_macro::{Delimiter, Span, TokenStream, TokenTree};
+
+const KEY_ENV: &str = "AES_MACRO_KEY";
+
+// encrypted_str!("...") takes one string literal, raw or not, and gives an
+// aes::secret::EncryptedStr that derefs to str.
+#[proc_macro]
+pub fn encrypted_str(input: TokenStream) -> TokenStream {
+    let plain: _ = match literal(&input) {
+        Some(lit) if lit.starts_with('"') || lit.starts_with('r') => format!("{}.as_bytes()", lit),
+        _ => return error("encrypted_str! takes a single string literal"),
+    };
+    expand(&input, &plain, "EncryptedStr")
+}
+
+// encrypted_bytes!(...) takes a byte string literal or an array of u8, and gives an
+// aes::secret::EncryptedBytes that derefs to [u8].
+#[proc_macro]
+pub fn encrypted_bytes(input: TokenStream) -> TokenStream {
+    let plain: _ = match literal(&input) {
+        Some(lit) if lit.starts_with('b') => lit,
+        Some(_) => return error("encrypted_bytes! takes a byte string literal or an array of u8"),
+        None if input.is_empty() => return error("encrypted_bytes! takes a byte string literal or an array of u8"),
+        None => format!("&{}", input),
+    };
+    expand(&input, &plain, "EncryptedBytes")
+}
+
+fn expand(input: &TokenStream, plain: &str, ty: &str) -> TokenStream {
+    let span: _ = Span::call_site();
+    let site: _ = format!("{}:{}:{}:{}", span.file(), span.line(), span.column(), input);
+    let ((key, iv), random): _ = match build_key() {
+        Ok((build_key, random)) => (derive(build_key, &site), random),
+        Err(reason) => return error(&reason),
+    };
+    // a use of a deprecated item is the only warning a stable proc macro can raise.
+    let warning: _ = match random {
+        true => "const _: () = ::aes::secret::RANDOM_BUILD_KEY;",
+        false => "",
+    };
+    format!(
+        "{{
+            {warning}
+            const __AES_PLAIN: &[u8] = {plain};
+            const __AES_CIPHER: [u8; __AES_PLAIN.len()] = ::aes::secret::ctr_const({key:?}, {iv:?}, __AES_PLAIN);
+            ::aes::secret::{ty}::new({key:?}, {iv:?}, __AES_CIPHER)
+        }}",
+        warning = warning, plain = plain, key = key, iv = iv, ty = ty
+    )
+    .parse()
+    .expect("attempting to expand an encrypted literal: the expansion is valid Rust.")
+}
+
+// the text of the one literal in the input, looking through the invisible groups that
+// macro_rules wraps around captured fragments.
+fn literal(input: &TokenStream) -> Option<String> {
+    let mut tokens: _ = input.clone().into_iter();
+    let token: _ = tokens.next()?;
+    if tokens.next().is_some() {
+        return None
+    }
+    match token {
+        TokenTree::Literal(lit) => Some(lit.to_string()),
+        TokenTree::Group(group) if group.delimiter() == Delimiter::None => literal(&group.stream()),
+        _ => None,
+    }
+}
+
+fn error(reason: &str) -> TokenStream {
+    format!("compile_error!({:?})", reason)
+        .parse()
+        .expect("attempting to report a macro error: compile_error! is valid Rust.")
+}
+
+// read or drawn once per compiler process, so that every call site derives from the same key.
+// the flag is set when the key was drawn at random.
+fn build_key() -> Result<(Block, bool), String> {
+    static KEY: OnceLock<Result<(Block, bool), String>> = OnceLock::new();
+    KEY.get_or_init(|| match env::var(KEY_ENV) {
+        Ok(hex) => parse_key(&hex).map(|key| (key, false)),
+        Err(_) => {
+            let mut key: Block = [0; BLOCK_SIZE];
+            OsEntropy.fill_entropy(&mut key)
+                .map(|_| (key, true))
+                .map_err(|err| format!("could not draw a build key: {}", err))
+        },
+    })
+    .clone()
+}
+
+fn parse_key(hex: &str) -> Result<Block, String> {
+    let hex: _ = hex.trim();
+    let invalid: _ = || format!("{} must be 32 hex digits", KEY_ENV);
+    if hex.len() != 2 * BLOCK_SIZE || !hex.is_ascii() {
+        return Err(invalid())
+    }
+    let mut key: Block = [0; BLOCK_SIZE];
+    for (val, idx) in key.iter_mut().zip((0..hex.len()).step_by(2)) {
+        *val = u8::from_str_radix(&hex[idx..idx + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(key)
+}
+
+// the key and the IV of one call site.
+fn derive(build_key: Block, site: &str) -> (Block, Block) {
+    let mut out: [u8; 2 * BLOCK_SIZE] = [0; 2 * BLOCK_SIZE];
+    kdf::Kbkdf::new(build_key, kdf::Mode::Counter)
+        .derive(b"aes-macros call site", site.as_bytes(), &mut out)
+        .expect("attempting to derive a call site key: two blocks are within the KDF's limit.");
+    let mut key: Block = [0; BLOCK_SIZE];
+    let mut iv: Block = [0; BLOCK_SIZE];
+    key.copy_from_slice(&out[..BLOCK_SIZE]);
+    iv.copy_from_slice(&out[BLOCK_SIZE..]);
+    (key, iv)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_key() {
+        let key: _ = parse_key("000102030405060708090a0B0c0d0e0f\n").unwrap();
+        assert_eq!([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15], key);
+        assert!(parse_key("0001").is_err());
+        assert!(parse_key("zz0102030405060708090a0b0c0d0e0f").is_err());
+        assert!(parse_key("é0102030405060708090a0b0c0d0e0").is_err());
+    }
+
+    #[test]
+    fn test_derive() {
+        let build_key: Block = [7; BLOCK_SIZE];
+        let (key, iv): _ = derive(build_key, "src/main.rs:3:14:\"endpoint\"");
+        assert_eq!((key, iv), derive(build_key, "src/main.rs:3:14:\"endpoint\""));
+        assert_ne!(key, iv);
+        assert_ne!((key, iv), derive(build_key, "src/main.rs:4:14:\"endpoint\""));
+        assert_ne!((key, iv), derive([8; BLOCK_SIZE], "src/main.rs:3:14:\"endpoint\""));
+    }
+}
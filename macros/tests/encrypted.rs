@@ -0,0 +1,45 @@
+#![allow(clippy::let_with_type_underscore)]
+// the tests build without AES_MACRO_KEY, so every literal warns about its random key.
+#![allow(deprecated)]
+
+use aes_macros::{encrypted_bytes, encrypted_str};
+
+#[test]
+fn test_str() {
+    let secret: _ = encrypted_str!("https://example.invalid/v1/endpoint?token=1");
+    assert!(!secret.is_decrypted());
+    assert_ne!(b"https://example.invalid/v1/endpoint?token=1", secret.ciphertext());
+    assert_eq!("https://example.invalid/v1/endpoint?token=1", &*secret);
+    assert!(secret.is_decrypted());
+
+    // the compiler unescapes the literal before it is encrypted.
+    assert_eq!("tab\there \"quoted\"\n", &*encrypted_str!("tab\there \"quoted\"\n"));
+    assert_eq!(r#"raw "\n" kept"#, &*encrypted_str!(r#"raw "\n" kept"#));
+    assert_eq!("κλειδί 🔑", &*encrypted_str!("κλειδί 🔑"));
+    assert_eq!("", &*encrypted_str!(""));
+}
+
+#[test]
+fn test_bytes() {
+    assert_eq!(b"\x00\xffbytes", &*encrypted_bytes!(b"\x00\xffbytes"));
+    assert_eq!(&[1u8, 2, 3, 0xff][..], &*encrypted_bytes!([1, 2, 3, 0xff]));
+    assert_eq!(&[0u8; 40][..], &*encrypted_bytes!([0; 40]));
+    assert!(encrypted_bytes!(b"").is_empty());
+}
+
+macro_rules! wrapped {
+    ($lit:literal) => {
+        encrypted_str!($lit)
+    };
+}
+
+#[test]
+fn test_call_sites() {
+    // the same literal gets a different key and IV at every call site.
+    let first: _ = encrypted_str!("same");
+    let second: _ = encrypted_str!("same");
+    assert_ne!(first.ciphertext(), second.ciphertext());
+    assert_eq!(&*first, &*second);
+
+    assert_eq!("through macro_rules", &*wrapped!("through macro_rules"));
+}
@@ -0,0 +1,365 @@
+//! AES-128 on the x86-64 AES-NI instructions, picked when the cipher is made if the CPU
+//! reports the aes feature and otherwise falling back to the reference rounds. The ECB
+//! and CTR paths keep eight blocks in flight so that each aesenc overlaps the latency of
+//! the ones before it.
+
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+use crate::{modes::cbc::check_aligned, xor_block, Block, BLOCK_SIZE, Error, RjindaelCipher};
+
+const LANES: usize = 8;
+#[cfg(target_arch = "x86_64")]
+const ROUNDS: usize = 10;
+
+#[derive(Debug, Clone)]
+pub struct AesNi {
+    imp: Imp,
+}
+
+// one per cipher, so the size difference between the variants does not matter.
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+enum Imp {
+    #[cfg(target_arch = "x86_64")]
+    Hardware { keys: [__m128i; ROUNDS + 1], reverse_keys: [__m128i; ROUNDS + 1] },
+    Software(RjindaelCipher),
+}
+
+impl AesNi {
+    pub fn new(key: Block) -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if has_aes() {
+                // the aes feature was detected above.
+                let (keys, reverse_keys): _ = unsafe { expand(key) };
+                return Self { imp: Imp::Hardware { keys, reverse_keys } };
+            }
+        }
+        Self::software(key)
+    }
+
+    // the fallback, whatever the CPU supports.
+    pub fn software(key: Block) -> Self {
+        Self { imp: Imp::Software(RjindaelCipher::new(key)) }
+    }
+
+    pub fn is_hardware(&self) -> bool {
+        !matches!(self.imp, Imp::Software(_))
+    }
+
+    pub fn encrypt_block(&self, block: &mut Block) {
+        self.encrypt_blocks(core::slice::from_mut(block))
+    }
+
+    pub fn decrypt_block(&self, block: &mut Block) {
+        self.decrypt_blocks(core::slice::from_mut(block))
+    }
+
+    pub fn encrypt_blocks(&self, blocks: &mut [Block]) {
+        match &self.imp {
+            // a Hardware cipher is only made once the aes feature is detected.
+            #[cfg(target_arch = "x86_64")]
+            Imp::Hardware { keys, .. } => unsafe { encrypt_blocks(keys, blocks) },
+            Imp::Software(cipher) => blocks.iter_mut().for_each(|block| cipher.encrypt_block(block)),
+        }
+    }
+
+    pub fn decrypt_blocks(&self, blocks: &mut [Block]) {
+        match &self.imp {
+            #[cfg(target_arch = "x86_64")]
+            Imp::Hardware { reverse_keys, .. } => unsafe { decrypt_blocks(reverse_keys, blocks) },
+            Imp::Software(cipher) => blocks.iter_mut().for_each(|block| cipher.decrypt_block(block)),
+        }
+    }
+
+    // one block under each cipher. The lanes only run together in hardware when every one
+    // of them does.
+    pub(crate) fn encrypt_lanes<const N: usize>(lanes: &[Self; N], blocks: &mut [Block; N]) {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if let Some(keys) = hardware_keys(lanes, |imp| match imp {
+                Imp::Hardware { keys, .. } => Some(keys),
+                Imp::Software(_) => None,
+            }) {
+                // a Hardware cipher is only made once the aes feature is detected.
+                return unsafe { crypt_lanes(&keys, blocks, Dir::Encrypt) };
+            }
+        }
+        lanes.iter().zip(blocks.iter_mut()).for_each(|(lane, block)| lane.encrypt_block(block))
+    }
+
+    pub(crate) fn decrypt_lanes<const N: usize>(lanes: &[Self; N], blocks: &mut [Block; N]) {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if let Some(keys) = hardware_keys(lanes, |imp| match imp {
+                Imp::Hardware { reverse_keys, .. } => Some(reverse_keys),
+                Imp::Software(_) => None,
+            }) {
+                return unsafe { crypt_lanes(&keys, blocks, Dir::Decrypt) };
+            }
+        }
+        lanes.iter().zip(blocks.iter_mut()).for_each(|(lane, block)| lane.decrypt_block(block))
+    }
+
+    pub fn ecb_encrypt(&self, buf: &mut [u8]) -> Result<(), Error> {
+        check_aligned(buf)?;
+        self.ecb(buf, Self::encrypt_blocks);
+        Ok(())
+    }
+
+    pub fn ecb_decrypt(&self, buf: &mut [u8]) -> Result<(), Error> {
+        check_aligned(buf)?;
+        self.ecb(buf, Self::decrypt_blocks);
+        Ok(())
+    }
+
+    // counter mode as in modes::Ctr, with the whole block as a big-endian counter.
+    pub fn ctr_apply_keystream(&self, iv: Block, buf: &mut [u8]) {
+        let mut counter: _ = u128::from_be_bytes(iv);
+        for chunk in buf.chunks_mut(LANES * BLOCK_SIZE) {
+            let mut keystream: [Block; LANES] = [[0; BLOCK_SIZE]; LANES];
+            let count: _ = chunk.len().div_ceil(BLOCK_SIZE);
+            for block in keystream[..count].iter_mut() {
+                *block = counter.to_be_bytes();
+                counter = counter.wrapping_add(1);
+            }
+            self.encrypt_blocks(&mut keystream[..count]);
+
+            for (part, stream) in chunk.chunks_mut(BLOCK_SIZE).zip(keystream.iter()) {
+                let mut block: Block = [0; BLOCK_SIZE];
+                block[..part.len()].copy_from_slice(part);
+                xor_block(&mut block, stream);
+                part.copy_from_slice(&block[..part.len()]);
+            }
+        }
+    }
+
+    fn ecb(&self, buf: &mut [u8], f: fn(&Self, &mut [Block])) {
+        for chunk in buf.chunks_mut(LANES * BLOCK_SIZE) {
+            let mut blocks: [Block; LANES] = [[0; BLOCK_SIZE]; LANES];
+            let count: _ = chunk.len() / BLOCK_SIZE;
+            for (block, part) in blocks.iter_mut().zip(chunk.chunks(BLOCK_SIZE)) {
+                block.copy_from_slice(part)
+            }
+            f(self, &mut blocks[..count]);
+            for (block, part) in blocks.iter().zip(chunk.chunks_mut(BLOCK_SIZE)) {
+                part.copy_from_slice(block)
+            }
+        }
+    }
+}
+
+// run time detection needs std. Without it the aes feature has to be on at compile time.
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn has_aes() -> bool {
+    #[cfg(feature = "std")]
+    {
+        is_x86_feature_detected!("aes")
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        cfg!(target_feature = "aes")
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[derive(Clone, Copy)]
+enum Dir {
+    Encrypt,
+    Decrypt,
+}
+
+#[cfg(target_arch = "x86_64")]
+type Keys = [__m128i; ROUNDS + 1];
+
+// the schedule of every lane, or None if any of them fell back to software.
+#[cfg(target_arch = "x86_64")]
+fn hardware_keys<'a, F, const N: usize>(lanes: &'a [AesNi; N], f: F) -> Option<[&'a Keys; N]>
+where
+    F: Fn(&'a Imp) -> Option<&'a Keys>
+{
+    let keys: [Option<&Keys>; N] = core::array::from_fn(|idx| f(&lanes[idx].imp));
+    match keys.iter().all(Option::is_some) {
+        true => Some(keys.map(Option::unwrap)),
+        false => None,
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "aes,sse2")]
+unsafe fn expand(key: Block) -> ([__m128i; ROUNDS + 1], [__m128i; ROUNDS + 1]) {
+    // the round constant is an immediate, so each step is spelled out.
+    macro_rules! step {
+        ($prev:expr, $rcon:expr) => {
+            expand_step($prev, _mm_aeskeygenassist_si128($prev, $rcon))
+        };
+    }
+
+    let mut keys: [__m128i; ROUNDS + 1] = [_mm_setzero_si128(); ROUNDS + 1];
+    keys[0] = _mm_loadu_si128(key.as_ptr() as *const __m128i);
+    keys[1] = step!(keys[0], 0x01);
+    keys[2] = step!(keys[1], 0x02);
+    keys[3] = step!(keys[2], 0x04);
+    keys[4] = step!(keys[3], 0x08);
+    keys[5] = step!(keys[4], 0x10);
+    keys[6] = step!(keys[5], 0x20);
+    keys[7] = step!(keys[6], 0x40);
+    keys[8] = step!(keys[7], 0x80);
+    keys[9] = step!(keys[8], 0x1b);
+    keys[10] = step!(keys[9], 0x36);
+
+    // aesdec runs the equivalent inverse cipher, so the middle keys go through InvMixColumns.
+    let mut reverse_keys: [__m128i; ROUNDS + 1] = [_mm_setzero_si128(); ROUNDS + 1];
+    reverse_keys[0] = keys[ROUNDS];
+    for round in 1..ROUNDS {
+        reverse_keys[round] = _mm_aesimc_si128(keys[ROUNDS - round]);
+    }
+    reverse_keys[ROUNDS] = keys[0];
+    (keys, reverse_keys)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "aes,sse2")]
+unsafe fn expand_step(prev: __m128i, assist: __m128i) -> __m128i {
+    // broadcast SubWord(RotWord(w3)) ^ rcon and fold in the running XOR of the previous key.
+    let assist: _ = _mm_shuffle_epi32(assist, 0xff);
+    let mut key: _ = prev;
+    key = _mm_xor_si128(key, _mm_slli_si128(key, 4));
+    key = _mm_xor_si128(key, _mm_slli_si128(key, 4));
+    key = _mm_xor_si128(key, _mm_slli_si128(key, 4));
+    _mm_xor_si128(key, assist)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "aes,sse2")]
+unsafe fn encrypt_blocks(keys: &[__m128i; ROUNDS + 1], blocks: &mut [Block]) {
+    for batch in blocks.chunks_mut(LANES) {
+        let mut state: [__m128i; LANES] = [_mm_setzero_si128(); LANES];
+        for (lane, block) in state.iter_mut().zip(batch.iter()) {
+            *lane = _mm_xor_si128(_mm_loadu_si128(block.as_ptr() as *const __m128i), keys[0])
+        }
+        for key in keys[1..ROUNDS].iter() {
+            for lane in state.iter_mut() {
+                *lane = _mm_aesenc_si128(*lane, *key)
+            }
+        }
+        for (lane, block) in state.iter().zip(batch.iter_mut()) {
+            _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, _mm_aesenclast_si128(*lane, keys[ROUNDS]))
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "aes,sse2")]
+unsafe fn decrypt_blocks(reverse_keys: &[__m128i; ROUNDS + 1], blocks: &mut [Block]) {
+    for batch in blocks.chunks_mut(LANES) {
+        let mut state: [__m128i; LANES] = [_mm_setzero_si128(); LANES];
+        for (lane, block) in state.iter_mut().zip(batch.iter()) {
+            *lane = _mm_xor_si128(_mm_loadu_si128(block.as_ptr() as *const __m128i), reverse_keys[0])
+        }
+        for key in reverse_keys[1..ROUNDS].iter() {
+            for lane in state.iter_mut() {
+                *lane = _mm_aesdec_si128(*lane, *key)
+            }
+        }
+        for (lane, block) in state.iter().zip(batch.iter_mut()) {
+            _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, _mm_aesdeclast_si128(*lane, reverse_keys[ROUNDS]))
+        }
+    }
+}
+
+// each lane under its own schedule, round by round across the lanes.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "aes,sse2")]
+unsafe fn crypt_lanes<const N: usize>(keys: &[&Keys; N], blocks: &mut [Block; N], dir: Dir) {
+    let mut state: [__m128i; N] = [_mm_setzero_si128(); N];
+    for ((lane, block), key) in state.iter_mut().zip(blocks.iter()).zip(keys.iter()) {
+        *lane = _mm_xor_si128(_mm_loadu_si128(block.as_ptr() as *const __m128i), key[0])
+    }
+    for round in 1..ROUNDS {
+        for (lane, key) in state.iter_mut().zip(keys.iter()) {
+            *lane = match dir {
+                Dir::Encrypt => _mm_aesenc_si128(*lane, key[round]),
+                Dir::Decrypt => _mm_aesdec_si128(*lane, key[round]),
+            }
+        }
+    }
+    for ((lane, block), key) in state.iter().zip(blocks.iter_mut()).zip(keys.iter()) {
+        let last: _ = match dir {
+            Dir::Encrypt => _mm_aesenclast_si128(*lane, key[ROUNDS]),
+            Dir::Decrypt => _mm_aesdeclast_si128(*lane, key[ROUNDS]),
+        };
+        _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, last)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{modes, test_util::unhex};
+
+    const KEY: Block = [
+        0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6,
+        0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c
+    ];
+
+    #[test]
+    fn test_fips_vector() {
+        // FIPS-197 Appendix C.1, on whichever implementation the CPU allows and the fallback.
+        let key: Block = [
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+            0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f
+        ];
+        let plain: Block = [
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77,
+            0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff
+        ];
+        let exp: Block = [
+            0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30,
+            0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5, 0x5a
+        ];
+        for cipher in [AesNi::new(key), AesNi::software(key)].iter() {
+            let mut test: Block = plain;
+            cipher.encrypt_block(&mut test);
+            assert_eq!(exp, test);
+            cipher.decrypt_block(&mut test);
+            assert_eq!(plain, test);
+        }
+        assert!(!AesNi::software(key).is_hardware());
+    }
+
+    #[test]
+    fn test_ecb() {
+        // SP 800-38A F.1.1, then a run that is not a whole number of batches.
+        let cipher: _ = AesNi::new(KEY);
+        let mut test: _ = unhex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51");
+        cipher.ecb_encrypt(&mut test).unwrap();
+        assert_eq!(unhex("3ad77bb40d7a3660a89ecaf32466ef97f5d3d58503b9699de785895a96fdbaaf"), test);
+
+        let ecb: _ = modes::Ecb::new(RjindaelCipher::new(KEY));
+        let plain: Vec<u8> = (0..21 * BLOCK_SIZE).map(|idx| idx as u8).collect();
+        let mut exp: _ = plain.clone();
+        let mut test: _ = plain.clone();
+        ecb.encrypt(&mut exp).unwrap();
+        cipher.ecb_encrypt(&mut test).unwrap();
+        assert_eq!(exp, test);
+        cipher.ecb_decrypt(&mut test).unwrap();
+        assert_eq!(plain, test);
+        assert_eq!(Err(Error::UnalignedInput(17)), cipher.ecb_encrypt(&mut [0; 17]));
+    }
+
+    #[test]
+    fn test_ctr() {
+        // the same keystream as modes::Ctr, across the counter wrap and with a partial block.
+        let iv: Block = [0xff; 16];
+        let ctr: _ = modes::Ctr::new(RjindaelCipher::new(KEY), iv);
+        for cipher in [AesNi::new(KEY), AesNi::software(KEY)].iter() {
+            let mut exp: _ = vec![0x5a; 300];
+            let mut test: _ = exp.clone();
+            ctr.apply_keystream(&mut exp);
+            cipher.ctr_apply_keystream(iv, &mut test);
+            assert_eq!(exp, test);
+        }
+    }
+}
@@ -0,0 +1,338 @@
+//! A bitsliced AES-128 that processes eight blocks at once with no secret dependent
+//! branches or memory accesses. Plane b holds bit b of every byte: bit 8p + k of a plane
+//! belongs to byte p of block k. SubBytes inverts in GF(2^8) as a^254 through a chain of
+//! bitsliced multiplications, then applies the affine map as plane XORs.
+
+use crate::{Block, BLOCK_SIZE};
+
+const LANES: usize = 8;
+const ROUNDS: usize = 10;
+
+type Planes = [u128; 8];
+
+#[derive(Debug, Clone)]
+pub struct Bitsliced {
+    keys: [Planes; ROUNDS + 1],
+}
+
+impl Bitsliced {
+    pub fn new(key: Block) -> Self {
+        let mut keys: [Planes; ROUNDS + 1] = [[0; 8]; ROUNDS + 1];
+        for (planes, round_key) in keys.iter_mut().zip(expand(key).iter()) {
+            *planes = broadcast(round_key)
+        }
+        Self { keys }
+    }
+
+    pub fn encrypt_block(&self, block: &mut Block) {
+        self.encrypt_blocks(core::slice::from_mut(block))
+    }
+
+    pub fn decrypt_block(&self, block: &mut Block) {
+        self.decrypt_blocks(core::slice::from_mut(block))
+    }
+
+    pub fn encrypt_blocks(&self, blocks: &mut [Block]) {
+        for batch in blocks.chunks_mut(LANES) {
+            let mut state: _ = pack(batch);
+            add_key(&mut state, &self.keys[0]);
+            for round in 1..=ROUNDS {
+                sub_bytes(&mut state);
+                shift_rows(&mut state);
+                if round != ROUNDS {
+                    mix_columns(&mut state);
+                }
+                add_key(&mut state, &self.keys[round]);
+            }
+            unpack(&state, batch)
+        }
+    }
+
+    pub fn decrypt_blocks(&self, blocks: &mut [Block]) {
+        for batch in blocks.chunks_mut(LANES) {
+            let mut state: _ = pack(batch);
+            add_key(&mut state, &self.keys[ROUNDS]);
+            for round in (0..ROUNDS).rev() {
+                inv_shift_rows(&mut state);
+                inv_sub_bytes(&mut state);
+                add_key(&mut state, &self.keys[round]);
+                if round != 0 {
+                    inv_mix_columns(&mut state);
+                }
+            }
+            unpack(&state, batch)
+        }
+    }
+}
+
+fn pack(blocks: &[Block]) -> Planes {
+    let mut planes: Planes = [0; 8];
+    for (lane, block) in blocks.iter().enumerate() {
+        for (pos, byte) in block.iter().enumerate() {
+            for (bit, plane) in planes.iter_mut().enumerate() {
+                *plane |= (((byte >> bit) & 1) as u128) << (8 * pos + lane)
+            }
+        }
+    }
+    planes
+}
+
+fn unpack(planes: &Planes, blocks: &mut [Block]) {
+    for (lane, block) in blocks.iter_mut().enumerate() {
+        for (pos, byte) in block.iter_mut().enumerate() {
+            *byte = planes.iter()
+                .enumerate()
+                .fold(0, |acc, (bit, plane)| acc | ((((plane >> (8 * pos + lane)) & 1) as u8) << bit))
+        }
+    }
+}
+
+// a round key copied into every lane.
+fn broadcast(key: &Block) -> Planes {
+    let mut planes: Planes = [0; 8];
+    for (pos, byte) in key.iter().enumerate() {
+        for (bit, plane) in planes.iter_mut().enumerate() {
+            *plane |= ((((byte >> bit) & 1) as u128) * 0xff) << (8 * pos)
+        }
+    }
+    planes
+}
+
+fn add_key(state: &mut Planes, key: &Planes) {
+    for (plane, val) in state.iter_mut().zip(key.iter()) {
+        *plane ^= val
+    }
+}
+
+// multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1, on every byte at once.
+fn gf_mul(a: &Planes, b: &Planes) -> Planes {
+    let mut prod: [u128; 15] = [0; 15];
+    for i in 0..8 {
+        for j in 0..8 {
+            prod[i + j] ^= a[i] & b[j]
+        }
+    }
+    for k in (8..15).rev() {
+        prod[k - 4] ^= prod[k];
+        prod[k - 5] ^= prod[k];
+        prod[k - 7] ^= prod[k];
+        prod[k - 8] ^= prod[k];
+    }
+    let mut planes: Planes = [0; 8];
+    planes.copy_from_slice(&prod[..8]);
+    planes
+}
+
+// a^254, which is the inverse of a and maps zero to zero.
+fn gf_inv(a: &Planes) -> Planes {
+    let x2: _ = gf_mul(a, a);
+    let x3: _ = gf_mul(&x2, a);
+    let x6: _ = gf_mul(&x3, &x3);
+    let x12: _ = gf_mul(&x6, &x6);
+    let x15: _ = gf_mul(&x12, &x3);
+    let mut x240: _ = x15;
+    for _ in 0..4 {
+        x240 = gf_mul(&x240, &x240);
+    }
+    let x252: _ = gf_mul(&x240, &x12);
+    gf_mul(&x252, &x2)
+}
+
+fn sub_bytes(state: &mut Planes) {
+    let inv: _ = gf_inv(state);
+    for bit in 0..8 {
+        let constant: u128 = match (0x63 >> bit) & 1 { 1 => !0, _ => 0 };
+        state[bit] = inv[bit] ^ inv[(bit + 4) % 8] ^ inv[(bit + 5) % 8]
+            ^ inv[(bit + 6) % 8] ^ inv[(bit + 7) % 8] ^ constant;
+    }
+}
+
+fn inv_sub_bytes(state: &mut Planes) {
+    let mut affine: Planes = [0; 8];
+    for (bit, plane) in affine.iter_mut().enumerate() {
+        let constant: u128 = match (0x05 >> bit) & 1 { 1 => !0, _ => 0 };
+        *plane = state[(bit + 2) % 8] ^ state[(bit + 5) % 8] ^ state[(bit + 7) % 8] ^ constant;
+    }
+    *state = gf_inv(&affine);
+}
+
+// moves every byte position, where byte 4c + r of a block sits in column c and row r.
+fn permute<F: Fn(usize, usize) -> usize>(state: &mut Planes, source: F) {
+    for plane in state.iter_mut() {
+        let old: _ = *plane;
+        *plane = 0;
+        for col in 0..4 {
+            for row in 0..4 {
+                let from: _ = source(col, row);
+                *plane |= ((old >> (8 * from)) & 0xff) << (8 * (4 * col + row))
+            }
+        }
+    }
+}
+
+fn shift_rows(state: &mut Planes) {
+    permute(state, |col, row| 4 * ((col + row) % 4) + row)
+}
+
+fn inv_shift_rows(state: &mut Planes) {
+    permute(state, |col, row| 4 * ((col + 4 - row) % 4) + row)
+}
+
+// the byte k rows further down the same column.
+fn rotate(state: &Planes, k: usize) -> Planes {
+    let mut buf: Planes = *state;
+    permute(&mut buf, |col, row| 4 * col + (row + k) % 4);
+    buf
+}
+
+fn xtime(state: &Planes) -> Planes {
+    [
+        state[7],
+        state[0] ^ state[7],
+        state[1],
+        state[2] ^ state[7],
+        state[3] ^ state[7],
+        state[4],
+        state[5],
+        state[6]
+    ]
+}
+
+fn xor(lhs: &Planes, rhs: &Planes) -> Planes {
+    let mut buf: Planes = *lhs;
+    add_key(&mut buf, rhs);
+    buf
+}
+
+// 2a_r + 3a_{r+1} + a_{r+2} + a_{r+3}
+fn mix_columns(state: &mut Planes) {
+    let (r1, r2, r3): _ = (rotate(state, 1), rotate(state, 2), rotate(state, 3));
+    let doubled: _ = xtime(&xor(state, &r1));
+    *state = xor(&xor(&doubled, &r1), &xor(&r2, &r3));
+}
+
+// 14a_r + 11a_{r+1} + 13a_{r+2} + 9a_{r+3}
+fn inv_mix_columns(state: &mut Planes) {
+    let x2: _ = xtime(state);
+    let x4: _ = xtime(&x2);
+    let x8: _ = xtime(&x4);
+    let e: _ = xor(&xor(&x8, &x4), &x2);
+    let b: _ = xor(&xor(&x8, &x2), state);
+    let d: _ = xor(&xor(&x8, &x4), state);
+    let n: _ = xor(&x8, state);
+    *state = xor(&xor(&e, &rotate(&b, 1)), &xor(&rotate(&d, 2), &rotate(&n, 3)));
+}
+
+// FIPS-197 key expansion, with SubWord run through the bitsliced S-box.
+fn expand(key: Block) -> [Block; ROUNDS + 1] {
+    let mut words: [[u8; 4]; 4 * (ROUNDS + 1)] = [[0; 4]; 4 * (ROUNDS + 1)];
+    for (word, chunk) in words.iter_mut().zip(key.chunks(4)) {
+        word.copy_from_slice(chunk)
+    }
+    let mut rcon: u8 = 1;
+    for idx in 4..words.len() {
+        let mut tmp: _ = words[idx - 1];
+        if idx % 4 == 0 {
+            tmp.rotate_left(1);
+            tmp = sub_word(tmp);
+            tmp[0] ^= rcon;
+            rcon = (rcon << 1) ^ (0x1b * (rcon >> 7));
+        }
+        for (val, prev) in tmp.iter_mut().zip(words[idx - 4].iter()) {
+            *val ^= prev
+        }
+        words[idx] = tmp;
+    }
+
+    let mut keys: [Block; ROUNDS + 1] = [[0; BLOCK_SIZE]; ROUNDS + 1];
+    for (round_key, chunk) in keys.iter_mut().zip(words.chunks(4)) {
+        for (dst, word) in round_key.chunks_mut(4).zip(chunk.iter()) {
+            dst.copy_from_slice(word)
+        }
+    }
+    keys
+}
+
+fn sub_word(word: [u8; 4]) -> [u8; 4] {
+    let mut block: Block = [0; BLOCK_SIZE];
+    block[..4].copy_from_slice(&word);
+    let mut state: _ = pack(core::slice::from_ref(&block));
+    sub_bytes(&mut state);
+    unpack(&state, core::slice::from_mut(&mut block));
+    let mut buf: [u8; 4] = [0; 4];
+    buf.copy_from_slice(&block[..4]);
+    buf
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::RjindaelCipher;
+
+    #[test]
+    fn test_fips_vector() {
+        // FIPS-197 Appendix C.1
+        let cipher: _ = Bitsliced::new([
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+            0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f
+        ]);
+        let mut test: Block = [
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77,
+            0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff
+        ];
+        cipher.encrypt_block(&mut test);
+        let exp: Block = [
+            0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30,
+            0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5, 0x5a
+        ];
+        assert_eq!(exp, test);
+        cipher.decrypt_block(&mut test);
+        assert_eq!([0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff], test);
+    }
+
+    #[test]
+    fn test_sbox() {
+        // every byte against the reference table.
+        let sbox: _ = crate::primitives::sbox::SubBox::default();
+        let mut blocks: [Block; 16] = [[0; BLOCK_SIZE]; 16];
+        for (idx, block) in blocks.iter_mut().enumerate() {
+            for (pos, byte) in block.iter_mut().enumerate() {
+                *byte = (16 * idx + pos) as u8
+            }
+        }
+        for batch in blocks.chunks_mut(LANES) {
+            let mut state: _ = pack(batch);
+            sub_bytes(&mut state);
+            unpack(&state, batch);
+        }
+        for (idx, byte) in blocks.iter().flat_map(|block| block.iter()).enumerate() {
+            assert_eq!(sbox[idx as u8].as_inner(), *byte);
+        }
+    }
+
+    #[test]
+    fn test_matches_reference() {
+        let key: Block = *b"bitsliced key 01";
+        let reference: _ = RjindaelCipher::new(key);
+        let cipher: _ = Bitsliced::new(key);
+
+        // batches that fill some, all and more than all of the lanes.
+        for count in [1, 5, 8, 13].iter() {
+            let mut blocks: Vec<Block> = (0..*count)
+                .map(|idx| [(idx * 31) as u8; BLOCK_SIZE])
+                .collect();
+            let mut exp: _ = blocks.clone();
+            for block in exp.iter_mut() {
+                reference.encrypt_block(block)
+            }
+            cipher.encrypt_blocks(&mut blocks);
+            assert_eq!(exp, blocks);
+
+            for block in exp.iter_mut() {
+                reference.decrypt_block(block)
+            }
+            cipher.decrypt_blocks(&mut blocks);
+            assert_eq!(exp, blocks);
+        }
+    }
+}
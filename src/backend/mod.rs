@@ -0,0 +1,13 @@
+mod aesni;
+mod bitslice;
+mod select;
+#[cfg(feature = "ttable")]
+mod ttable;
+
+pub use aesni::AesNi;
+pub use bitslice::Bitsliced;
+pub use select::{Backend, Kind, BACKEND_ENV, CHECK_ENV};
+#[cfg(feature = "std")]
+pub use select::{select, select_with, CrossCheck};
+#[cfg(feature = "ttable")]
+pub use ttable::TTable;
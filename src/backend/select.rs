@@ -0,0 +1,368 @@
+//! The Backend trait over every AES-128 implementation, and the selection of one at run
+//! time. Selection takes AES_BACKEND if it is set, then AES-NI if the CPU has it, then the
+//! T-tables if the ttable feature is on, and the bitsliced backend otherwise. Setting
+//! AES_BACKEND_CHECK to a second backend runs both on every call and panics on divergence.
+//! The boxed backends, and with them select and CrossCheck, need the std feature.
+
+#[cfg(feature = "std")]
+use std::env;
+
+use crate::{backend, Block, Error, RjindaelCipher};
+
+pub const BACKEND_ENV: &str = "AES_BACKEND";
+pub const CHECK_ENV: &str = "AES_BACKEND_CHECK";
+
+pub trait Backend: Send + Sync {
+    // expands the key, once, for every block the backend processes after.
+    fn expand_key(key: Block) -> Self where Self: Sized;
+
+    fn name(&self) -> &'static str;
+
+    fn encrypt_blocks(&self, blocks: &mut [Block]);
+
+    fn decrypt_blocks(&self, blocks: &mut [Block]);
+
+    fn encrypt_block(&self, block: &mut Block) {
+        self.encrypt_blocks(core::slice::from_mut(block))
+    }
+
+    fn decrypt_block(&self, block: &mut Block) {
+        self.decrypt_blocks(core::slice::from_mut(block))
+    }
+
+    // one block under each of N ciphers. The default runs the lanes one after the other;
+    // a backend that can overlap the rounds of independent keys overrides it.
+    fn encrypt_lanes<const N: usize>(lanes: &[Self; N], blocks: &mut [Block; N]) where Self: Sized {
+        lanes.iter().zip(blocks.iter_mut()).for_each(|(lane, block)| lane.encrypt_block(block))
+    }
+
+    fn decrypt_lanes<const N: usize>(lanes: &[Self; N], blocks: &mut [Block; N]) where Self: Sized {
+        lanes.iter().zip(blocks.iter_mut()).for_each(|(lane, block)| lane.decrypt_block(block))
+    }
+}
+
+// the Round and ReverseRound reference path.
+impl Backend for RjindaelCipher {
+    fn expand_key(key: Block) -> Self {
+        Self::new(key)
+    }
+
+    fn name(&self) -> &'static str {
+        "reference"
+    }
+
+    fn encrypt_blocks(&self, blocks: &mut [Block]) {
+        blocks.iter_mut().for_each(|block| RjindaelCipher::encrypt_block(self, block))
+    }
+
+    fn decrypt_blocks(&self, blocks: &mut [Block]) {
+        blocks.iter_mut().for_each(|block| RjindaelCipher::decrypt_block(self, block))
+    }
+
+    fn encrypt_lanes<const N: usize>(lanes: &[Self; N], blocks: &mut [Block; N]) {
+        RjindaelCipher::encrypt_lanes(lanes, blocks)
+    }
+
+    fn decrypt_lanes<const N: usize>(lanes: &[Self; N], blocks: &mut [Block; N]) {
+        RjindaelCipher::decrypt_lanes(lanes, blocks)
+    }
+}
+
+impl Backend for backend::Bitsliced {
+    fn expand_key(key: Block) -> Self {
+        Self::new(key)
+    }
+
+    fn name(&self) -> &'static str {
+        "bitsliced"
+    }
+
+    fn encrypt_blocks(&self, blocks: &mut [Block]) {
+        backend::Bitsliced::encrypt_blocks(self, blocks)
+    }
+
+    fn decrypt_blocks(&self, blocks: &mut [Block]) {
+        backend::Bitsliced::decrypt_blocks(self, blocks)
+    }
+}
+
+impl Backend for backend::AesNi {
+    fn expand_key(key: Block) -> Self {
+        Self::new(key)
+    }
+
+    fn name(&self) -> &'static str {
+        "aesni"
+    }
+
+    fn encrypt_blocks(&self, blocks: &mut [Block]) {
+        backend::AesNi::encrypt_blocks(self, blocks)
+    }
+
+    fn decrypt_blocks(&self, blocks: &mut [Block]) {
+        backend::AesNi::decrypt_blocks(self, blocks)
+    }
+
+    fn encrypt_lanes<const N: usize>(lanes: &[Self; N], blocks: &mut [Block; N]) {
+        backend::AesNi::encrypt_lanes(lanes, blocks)
+    }
+
+    fn decrypt_lanes<const N: usize>(lanes: &[Self; N], blocks: &mut [Block; N]) {
+        backend::AesNi::decrypt_lanes(lanes, blocks)
+    }
+}
+
+#[cfg(feature = "ttable")]
+impl Backend for backend::TTable {
+    fn expand_key(key: Block) -> Self {
+        Self::new(key)
+    }
+
+    fn name(&self) -> &'static str {
+        "ttable"
+    }
+
+    fn encrypt_blocks(&self, blocks: &mut [Block]) {
+        backend::TTable::encrypt_blocks(self, blocks)
+    }
+
+    fn decrypt_blocks(&self, blocks: &mut [Block]) {
+        backend::TTable::decrypt_blocks(self, blocks)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Reference,
+    Bitsliced,
+    AesNi,
+    #[cfg(feature = "ttable")]
+    TTable,
+}
+
+impl Kind {
+    pub fn from_name(name: &str) -> Result<Self, Error> {
+        match name {
+            "reference" => Ok(Kind::Reference),
+            "bitsliced" => Ok(Kind::Bitsliced),
+            "aesni" => Ok(Kind::AesNi),
+            #[cfg(feature = "ttable")]
+            "ttable" => Ok(Kind::TTable),
+            _ => Err(Error::InvalidParameter("the backend name is unknown or not compiled in")),
+        }
+    }
+
+    // the backend named by AES_BACKEND, or the fastest one available.
+    #[cfg(feature = "std")]
+    pub fn detect() -> Result<Self, Error> {
+        Self::detect_with(env::var(BACKEND_ENV).ok().as_deref())
+    }
+
+    pub fn detect_with(name: Option<&str>) -> Result<Self, Error> {
+        if let Some(name) = name {
+            return Self::from_available_name(name);
+        }
+        if Kind::AesNi.is_available() {
+            return Ok(Kind::AesNi);
+        }
+        #[cfg(feature = "ttable")]
+        {
+            return Ok(Kind::TTable);
+        }
+        #[allow(unreachable_code)]
+        Ok(Kind::Bitsliced)
+    }
+
+    // the named backend, which must also run on this CPU.
+    pub fn from_available_name(name: &str) -> Result<Self, Error> {
+        let kind: _ = Self::from_name(name)?;
+        if !kind.is_available() {
+            return Err(Error::InvalidParameter("the backend is not supported by this CPU"));
+        }
+        Ok(kind)
+    }
+
+    pub fn is_available(self) -> bool {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            Kind::AesNi => backend::aesni::has_aes(),
+            #[cfg(not(target_arch = "x86_64"))]
+            Kind::AesNi => false,
+            _ => true,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn build(self, key: Block) -> Box<dyn Backend> {
+        match self {
+            Kind::Reference => Box::new(RjindaelCipher::expand_key(key)),
+            Kind::Bitsliced => Box::new(backend::Bitsliced::expand_key(key)),
+            Kind::AesNi => Box::new(backend::AesNi::expand_key(key)),
+            #[cfg(feature = "ttable")]
+            Kind::TTable => Box::new(backend::TTable::expand_key(key)),
+        }
+    }
+}
+
+// the backend chosen by detect, paired with the one in AES_BACKEND_CHECK when it is set.
+#[cfg(feature = "std")]
+pub fn select(key: Block) -> Result<Box<dyn Backend>, Error> {
+    select_with(key, env::var(BACKEND_ENV).ok().as_deref(), env::var(CHECK_ENV).ok().as_deref())
+}
+
+// both backends must run on this CPU, so an unsupported check backend is an error rather
+// than an illegal instruction.
+#[cfg(feature = "std")]
+pub fn select_with(key: Block, name: Option<&str>, check: Option<&str>) -> Result<Box<dyn Backend>, Error> {
+    let primary: _ = Kind::detect_with(name)?.build(key);
+    match check {
+        Some(check) => Ok(Box::new(CrossCheck::new(primary, Kind::from_available_name(check)?.build(key)))),
+        None => Ok(primary),
+    }
+}
+
+// runs every call on both backends and panics if their output differs. The primary's
+// output is the one returned.
+#[cfg(feature = "std")]
+pub struct CrossCheck {
+    primary: Box<dyn Backend>,
+    shadow: Box<dyn Backend>,
+}
+
+#[cfg(feature = "std")]
+impl CrossCheck {
+    pub fn new(primary: Box<dyn Backend>, shadow: Box<dyn Backend>) -> Self {
+        Self { primary, shadow }
+    }
+
+    fn check(&self, blocks: &mut [Block], op: fn(&dyn Backend, &mut [Block]), dir: &str) {
+        let mut shadow: _ = blocks.to_vec();
+        op(self.primary.as_ref(), blocks);
+        op(self.shadow.as_ref(), &mut shadow);
+        assert!(
+            blocks[..] == shadow[..],
+            "the {} and {} backends diverged while attempting to {}.",
+            self.primary.name(), self.shadow.name(), dir
+        );
+    }
+}
+
+#[cfg(feature = "std")]
+impl Backend for CrossCheck {
+    // checks the detected backend against the reference.
+    fn expand_key(key: Block) -> Self {
+        let primary: _ = Kind::detect().unwrap_or(Kind::Bitsliced).build(key);
+        Self::new(primary, Kind::Reference.build(key))
+    }
+
+    fn name(&self) -> &'static str {
+        "cross-check"
+    }
+
+    fn encrypt_blocks(&self, blocks: &mut [Block]) {
+        self.check(blocks, |backend, blocks| backend.encrypt_blocks(blocks), "encrypt")
+    }
+
+    fn decrypt_blocks(&self, blocks: &mut [Block]) {
+        self.check(blocks, |backend, blocks| backend.decrypt_blocks(blocks), "decrypt")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "std")]
+    use crate::BLOCK_SIZE;
+
+    const KINDS: &[&str] = &["reference", "bitsliced", "aesni", #[cfg(feature = "ttable")] "ttable"];
+
+    // flips one bit of every ciphertext, standing in for a broken backend.
+    #[cfg(feature = "std")]
+    struct Broken(RjindaelCipher);
+
+    #[cfg(feature = "std")]
+    impl Backend for Broken {
+        fn expand_key(key: Block) -> Self {
+            Broken(RjindaelCipher::new(key))
+        }
+
+        fn name(&self) -> &'static str {
+            "broken"
+        }
+
+        fn encrypt_blocks(&self, blocks: &mut [Block]) {
+            Backend::encrypt_blocks(&self.0, blocks);
+            blocks.iter_mut().for_each(|block| block[0] ^= 1)
+        }
+
+        fn decrypt_blocks(&self, blocks: &mut [Block]) {
+            Backend::decrypt_blocks(&self.0, blocks)
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_backends_agree() {
+        let key: Block = *b"backend selector";
+        let plain: Vec<Block> = (0..19u8).map(|idx| [idx.wrapping_mul(91); BLOCK_SIZE]).collect();
+        let mut exp: _ = plain.clone();
+        Kind::Reference.build(key).encrypt_blocks(&mut exp);
+
+        for name in KINDS.iter() {
+            let kind: _ = Kind::from_name(name).unwrap();
+            let backend: _ = kind.build(key);
+            assert_eq!(*name, backend.name());
+
+            let mut test: _ = plain.clone();
+            backend.encrypt_blocks(&mut test);
+            assert_eq!(exp, test);
+            backend.decrypt_blocks(&mut test);
+            assert_eq!(plain, test);
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Ok(Kind::Reference), Kind::detect_with(Some("reference")));
+        assert_eq!(Ok(Kind::Bitsliced), Kind::detect_with(Some("bitsliced")));
+        assert!(Kind::from_name("rot13").is_err());
+        assert!(KINDS.iter().all(|name| Kind::from_name(name).is_ok()));
+        assert!(Kind::detect_with(None).unwrap().is_available());
+        if Kind::AesNi.is_available() {
+            assert_eq!(Ok(Kind::AesNi), Kind::detect_with(None));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_select() {
+        let key: Block = [7; BLOCK_SIZE];
+        assert_eq!("reference", select_with(key, Some("reference"), None).unwrap().name());
+        assert_eq!("cross-check", select_with(key, Some("reference"), Some("bitsliced")).unwrap().name());
+        assert!(select_with(key, Some("reference"), Some("rot13")).is_err());
+        assert_eq!(
+            Kind::AesNi.is_available(),
+            select_with(key, Some("reference"), Some("aesni")).is_ok()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_cross_check() {
+        let key: Block = [7; BLOCK_SIZE];
+        let check: _ = CrossCheck::expand_key(key);
+        let mut block: Block = [0; BLOCK_SIZE];
+        check.encrypt_block(&mut block);
+        check.decrypt_block(&mut block);
+        assert_eq!([0; BLOCK_SIZE], block);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    #[should_panic(expected = "the broken and reference backends diverged")]
+    fn test_cross_check_divergence() {
+        let key: Block = [7; BLOCK_SIZE];
+        let check: _ = CrossCheck::new(Box::new(Broken::expand_key(key)), Kind::Reference.build(key));
+        check.encrypt_block(&mut [0; BLOCK_SIZE]);
+    }
+}
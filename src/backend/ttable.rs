@@ -0,0 +1,215 @@
+//! The 32-bit table implementation, where each column of a round is four lookups that merge
+//! SubBytes, ShiftRows and MixColumns. The tables are built at compile time from the S-boxes
+//! and GF(2^8) multiply of the reference path. Lookups are indexed by secret bytes, so this
+//! backend is only for data that is not sensitive to cache timing.
+
+use crate::primitives::{byte::Byte, sbox};
+use crate::Block;
+
+const ROUNDS: usize = 10;
+
+const TE: [[u32; 256]; 4] = tables(&sbox::SUB_BOX, [2, 1, 1, 3]);
+const TD: [[u32; 256]; 4] = tables(&sbox::REVERSE_SUB_BOX, [14, 9, 13, 11]);
+
+// table 0 maps x to the column (c0 s, c1 s, c2 s, c3 s) with s = box[x], and table i is
+// table 0 rotated right by i bytes.
+const fn tables(sbox: &[u8; 256], coeffs: [u8; 4]) -> [[u32; 256]; 4] {
+    let mut buf: [[u32; 256]; 4] = [[0; 256]; 4];
+    let mut idx: usize = 0;
+    while idx < 256 {
+        let s: Byte = Byte::new(sbox[idx]);
+        let word: u32 = u32::from_be_bytes([
+            s.gf_mul(Byte::new(coeffs[0])).as_inner(),
+            s.gf_mul(Byte::new(coeffs[1])).as_inner(),
+            s.gf_mul(Byte::new(coeffs[2])).as_inner(),
+            s.gf_mul(Byte::new(coeffs[3])).as_inner(),
+        ]);
+        let mut table: usize = 0;
+        while table < 4 {
+            buf[table][idx] = word.rotate_right(8 * table as u32);
+            table += 1;
+        }
+        idx += 1;
+    }
+    buf
+}
+
+#[derive(Debug, Clone)]
+pub struct TTable {
+    keys: [[u32; 4]; ROUNDS + 1],
+    reverse_keys: [[u32; 4]; ROUNDS + 1],
+}
+
+impl TTable {
+    pub fn new(key: Block) -> Self {
+        let keys: _ = expand(key);
+
+        // the equivalent inverse cipher runs the keys backwards with InvMixColumns applied
+        // to every key but the first and last.
+        let mut reverse_keys: [[u32; 4]; ROUNDS + 1] = [[0; 4]; ROUNDS + 1];
+        for (round, rkey) in reverse_keys.iter_mut().enumerate() {
+            *rkey = keys[ROUNDS - round];
+            if round != 0 && round != ROUNDS {
+                for word in rkey.iter_mut() {
+                    *word = inv_mix_word(*word)
+                }
+            }
+        }
+        Self { keys, reverse_keys }
+    }
+
+    pub fn encrypt_block(&self, block: &mut Block) {
+        let mut s: _ = load(block, &self.keys[0]);
+        for rkey in self.keys[1..ROUNDS].iter() {
+            s = round(&TE, &s, rkey, [1, 2, 3]);
+        }
+        let out: _ = last_round(&sbox::SUB_BOX, &s, &self.keys[ROUNDS], [1, 2, 3]);
+        store(&out, block)
+    }
+
+    pub fn decrypt_block(&self, block: &mut Block) {
+        let mut s: _ = load(block, &self.reverse_keys[0]);
+        for rkey in self.reverse_keys[1..ROUNDS].iter() {
+            s = round(&TD, &s, rkey, [3, 2, 1]);
+        }
+        let out: _ = last_round(&sbox::REVERSE_SUB_BOX, &s, &self.reverse_keys[ROUNDS], [3, 2, 1]);
+        store(&out, block)
+    }
+
+    pub fn encrypt_blocks(&self, blocks: &mut [Block]) {
+        blocks.iter_mut().for_each(|block| self.encrypt_block(block))
+    }
+
+    pub fn decrypt_blocks(&self, blocks: &mut [Block]) {
+        blocks.iter_mut().for_each(|block| self.decrypt_block(block))
+    }
+}
+
+fn load(block: &Block, rkey: &[u32; 4]) -> [u32; 4] {
+    let mut s: [u32; 4] = [0; 4];
+    for (col, word) in s.iter_mut().enumerate() {
+        *word = u32::from_be_bytes([block[4 * col], block[4 * col + 1], block[4 * col + 2], block[4 * col + 3]]) ^ rkey[col]
+    }
+    s
+}
+
+fn store(s: &[u32; 4], block: &mut Block) {
+    for (chunk, word) in block.chunks_mut(4).zip(s.iter()) {
+        chunk.copy_from_slice(&word.to_be_bytes())
+    }
+}
+
+// row r of output column c comes from column c + shift[r - 1], where the shifts undo
+// ShiftRows in one direction or the other.
+fn round(tables: &[[u32; 256]; 4], s: &[u32; 4], rkey: &[u32; 4], shift: [usize; 3]) -> [u32; 4] {
+    let mut buf: [u32; 4] = [0; 4];
+    for (col, word) in buf.iter_mut().enumerate() {
+        *word = tables[0][(s[col] >> 24) as usize]
+            ^ tables[1][((s[(col + shift[0]) % 4] >> 16) & 0xff) as usize]
+            ^ tables[2][((s[(col + shift[1]) % 4] >> 8) & 0xff) as usize]
+            ^ tables[3][(s[(col + shift[2]) % 4] & 0xff) as usize]
+            ^ rkey[col];
+    }
+    buf
+}
+
+fn last_round(sbox: &[u8; 256], s: &[u32; 4], rkey: &[u32; 4], shift: [usize; 3]) -> [u32; 4] {
+    let mut buf: [u32; 4] = [0; 4];
+    for (col, word) in buf.iter_mut().enumerate() {
+        *word = u32::from_be_bytes([
+            sbox[(s[col] >> 24) as usize],
+            sbox[((s[(col + shift[0]) % 4] >> 16) & 0xff) as usize],
+            sbox[((s[(col + shift[1]) % 4] >> 8) & 0xff) as usize],
+            sbox[(s[(col + shift[2]) % 4] & 0xff) as usize],
+        ]) ^ rkey[col];
+    }
+    buf
+}
+
+// InvMixColumns of one word, by looking each byte through the S-box into the decryption
+// tables, whose inverse S-box cancels it.
+fn inv_mix_word(word: u32) -> u32 {
+    let bytes: _ = word.to_be_bytes();
+    (0..4).fold(0, |acc, idx| acc ^ TD[idx][sbox::SUB_BOX[bytes[idx] as usize] as usize])
+}
+
+fn expand(key: Block) -> [[u32; 4]; ROUNDS + 1] {
+    let mut words: [u32; 4 * (ROUNDS + 1)] = [0; 4 * (ROUNDS + 1)];
+    for (word, chunk) in words.iter_mut().zip(key.chunks(4)) {
+        *word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])
+    }
+    let mut rcon: u8 = 1;
+    for idx in 4..words.len() {
+        let mut tmp: _ = words[idx - 1];
+        if idx % 4 == 0 {
+            let bytes: _ = tmp.rotate_left(8).to_be_bytes();
+            tmp = u32::from_be_bytes([
+                sbox::SUB_BOX[bytes[0] as usize] ^ rcon,
+                sbox::SUB_BOX[bytes[1] as usize],
+                sbox::SUB_BOX[bytes[2] as usize],
+                sbox::SUB_BOX[bytes[3] as usize],
+            ]);
+            rcon = Byte::new(rcon).gf_mul(Byte::new(2)).as_inner();
+        }
+        words[idx] = words[idx - 4] ^ tmp;
+    }
+
+    let mut keys: [[u32; 4]; ROUNDS + 1] = [[0; 4]; ROUNDS + 1];
+    for (rkey, chunk) in keys.iter_mut().zip(words.chunks(4)) {
+        rkey.copy_from_slice(chunk)
+    }
+    keys
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{RjindaelCipher, BLOCK_SIZE};
+
+    #[test]
+    fn test_tables() {
+        assert_eq!(0xc66363a5, TE[0][0x00]);
+        assert_eq!(0xa5c66363, TE[1][0x00]);
+        assert_eq!(0x51f4a750, TD[0][0x00]);
+        assert_eq!(0x5051f4a7, TD[1][0x00]);
+    }
+
+    #[test]
+    fn test_fips_vector() {
+        // FIPS-197 Appendix C.1
+        let cipher: _ = TTable::new([
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+            0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f
+        ]);
+        let plain: Block = [
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77,
+            0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff
+        ];
+        let mut test: Block = plain;
+        cipher.encrypt_block(&mut test);
+        let exp: Block = [
+            0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30,
+            0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5, 0x5a
+        ];
+        assert_eq!(exp, test);
+        cipher.decrypt_block(&mut test);
+        assert_eq!(plain, test);
+    }
+
+    #[test]
+    fn test_matches_reference() {
+        let key: Block = *b"t-table key 0123";
+        let reference: _ = RjindaelCipher::new(key);
+        let cipher: _ = TTable::new(key);
+
+        let mut blocks: Vec<Block> = (0..64u8).map(|idx| [idx.wrapping_mul(37); BLOCK_SIZE]).collect();
+        let mut exp: _ = blocks.clone();
+        exp.iter_mut().for_each(|block| reference.encrypt_block(block));
+        cipher.encrypt_blocks(&mut blocks);
+        assert_eq!(exp, blocks);
+
+        exp.iter_mut().for_each(|block| reference.decrypt_block(block));
+        cipher.decrypt_blocks(&mut blocks);
+        assert_eq!(exp, blocks);
+    }
+}
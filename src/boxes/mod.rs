@@ -1,5 +1,5 @@
 mod round;
 mod schedule;
 
-pub use round::{ReverseRound, Round};
+pub use round::{encrypt_const, ReverseRound, Round};
 pub use schedule::{KeySchedule, ReverseKeySchedule};
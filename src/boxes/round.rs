@@ -1,120 +1,195 @@
-use crate::primitives::{sbox, state, byte};
+use core::ops;
 
-pub struct Round {
-    sbox: sbox::SubBox,
-    state: state::State
+use crate::{primitives::{byte, sbox, word}, Block};
+
+// byte i of the block is row i % 4 of column i / 4, so a column is four bytes in a row.
+// ShiftRows moves row r left by r columns: byte 4c + r comes from column c + r.
+const SHIFT: [usize; 16] = [0, 5, 10, 15, 4, 9, 14, 3, 8, 13, 2, 7, 12, 1, 6, 11];
+const INV_SHIFT: [usize; 16] = [0, 13, 10, 7, 4, 1, 14, 11, 8, 5, 2, 15, 12, 9, 6, 3];
+
+// the rounds run in place on the block, in the FIPS-197 byte order, and a full round goes
+// one column at a time, from the table lookups through the mix in a register.
+pub struct Round<'a> {
+    sbox: &'a sbox::SubBox,
+    block: Block
 }
 
-impl Round {
-    pub fn new(state: state::State) -> Self {
-        Round {
-            state,
-            sbox: sbox::SubBox::default()
-        }
+impl<'a> Round<'a> {
+    pub fn new(block: Block, sbox: &'a sbox::SubBox) -> Self {
+        Round { block, sbox }
     }
 
-    pub fn next(&mut self, skey: &state::State) -> &state::State {
-        &self.sub_and_shift()
-            .mix()
-            .xor_with_key(skey)
-            .state
+    pub fn first(&mut self, skey: &Block) {
+        self.xor_with_key(skey);
     }
 
-    pub fn last(mut self, skey: &state::State) -> state::State {
+    pub fn next(&mut self, skey: &Block) {
+        let buf: Block = self.block;
+        for (c, col) in self.block.chunks_exact_mut(4).enumerate() {
+            let packed: _ = word::Word::MIX.mul_column(gather(&buf, &SHIFT, self.sbox, c));
+            col.copy_from_slice(&(packed ^ column(skey, c)).to_le_bytes())
+        }
+    }
+
+    pub fn last(mut self, skey: &Block) -> Block {
         self.sub_and_shift()
             .xor_with_key(skey)
-            .swap_state()
+            .block
     }
 
     fn sub_and_shift(&mut self) -> &mut Self {
-        self.state = self.swap_state()
-            .into_rows()
-            .enumerate()
-            .map(|(idx, row)| row.substitute(&self.sbox) << idx)
-            .collect();
+        let buf: Block = self.block;
+        for (c, col) in self.block.chunks_exact_mut(4).enumerate() {
+            col.copy_from_slice(&gather(&buf, &SHIFT, self.sbox, c).to_le_bytes())
+        }
         self
     }
 
+    // each column times a(x) modulo x^4 + 1, on its own for the tests.
+    #[cfg(test)]
     fn mix(&mut self) -> &mut Self {
-        let perm: _ = state::State::from([
-            0x02, 0x03, 0x01, 0x01,
-            0x01, 0x02, 0x03, 0x01,
-            0x01, 0x01, 0x02, 0x03,
-            0x03, 0x01, 0x01, 0x02
-        ]);
-        self.state.mix(perm);
+        mix_columns(&mut self.block, &word::Word::MIX);
         self
     }
 
-    fn xor_with_key(&mut self, skey: &state::State) -> &mut Self {
-        self.state = self.swap_state() ^ skey.clone();
+    fn xor_with_key(&mut self, skey: &Block) -> &mut Self {
+        crate::xor_block(&mut self.block, skey);
         self
     }
-
-    fn swap_state(&mut self) -> state::State {
-        std::mem::replace(&mut self.state, state::State::default())
-    } 
 }
 
-pub struct ReverseRound {
-    rsbox: sbox::ReverseSubBox,
-    state: state::State
+pub struct ReverseRound<'a> {
+    rsbox: &'a sbox::ReverseSubBox,
+    block: Block
 }
 
-impl ReverseRound {
-    pub fn new(state: state::State) -> Self {
-        ReverseRound {
-            state,
-            rsbox: sbox::ReverseSubBox::default()
-        }
+impl<'a> ReverseRound<'a> {
+    pub fn new(block: Block, rsbox: &'a sbox::ReverseSubBox) -> Self {
+        ReverseRound { block, rsbox }
     }
 
-    pub fn next(&mut self, skey: &state::State) -> &state::State {
-        &self.sub_and_shift()
-            .mix()
-            .xor_with_key(skey)
-            .state
+    pub fn first(&mut self, skey: &Block) {
+        self.xor_with_key(skey);
     }
 
-    pub fn last(mut self, skey: &state::State) -> state::State {
+    pub fn next(&mut self, skey: &Block) {
+        let buf: Block = self.block;
+        for (c, col) in self.block.chunks_exact_mut(4).enumerate() {
+            let packed: _ = gather(&buf, &INV_SHIFT, self.rsbox, c) ^ column(skey, c);
+            col.copy_from_slice(&word::Word::INV_MIX.mul_column(packed).to_le_bytes())
+        }
+    }
+
+    pub fn last(mut self, skey: &Block) -> Block {
         self.sub_and_shift()
             .xor_with_key(skey)
-            .swap_state()
+            .block
     }
 
     fn sub_and_shift(&mut self) -> &mut Self {
-        self.state = self.swap_state()
-            .into_rows()
-            .enumerate()
-            .map(|(idx, row)| row.substitute(&self.rsbox) >> idx)
-            .collect();
+        let buf: Block = self.block;
+        for (c, col) in self.block.chunks_exact_mut(4).enumerate() {
+            col.copy_from_slice(&gather(&buf, &INV_SHIFT, self.rsbox, c).to_le_bytes())
+        }
         self
     }
 
+    // each column times a^-1(x) modulo x^4 + 1, on its own for the tests.
+    #[cfg(test)]
     fn mix(&mut self) -> &mut Self {
-        let perm: _ = state::State::from([
-            0xe, 0xb, 0xd, 0x9,
-            0x9, 0xe, 0xb, 0xd,
-            0xd, 0x9, 0xe, 0xb,      
-            0xb, 0xd, 0x9, 0xe
-        ]);
-        self.state.mix(perm);
+        mix_columns(&mut self.block, &word::Word::INV_MIX);
         self
     }
 
-    fn swap_state(&mut self) -> state::State {
-        std::mem::replace(&mut self.state, state::State::default())
-    } 
-
-    fn xor_with_key(&mut self, skey: &state::State) -> &mut Self {
-        self.state = self.swap_state() ^ skey.clone();
+    fn xor_with_key(&mut self, skey: &Block) -> &mut Self {
+        crate::xor_block(&mut self.block, skey);
         self
     }
 }
 
+// the same rounds in const context, where the tables are indexed by hand and the steps
+// are while loops over the block.
+pub const fn encrypt_const(keys: &[Block; 11], block: Block) -> Block {
+    let mut block: Block = add_key_const(block, &keys[0]);
+    let mut round: usize = 1;
+    while round < 10 {
+        block = add_key_const(mix_const(sub_and_shift_const(block)), &keys[round]);
+        round += 1;
+    }
+    add_key_const(sub_and_shift_const(block), &keys[10])
+}
+
+const fn sub_and_shift_const(block: Block) -> Block {
+    let mut buf: Block = [0; 16];
+    let mut idx: usize = 0;
+    while idx < 16 {
+        buf[idx] = sbox::SUB_BOX[block[SHIFT[idx]] as usize];
+        idx += 1;
+    }
+    buf
+}
+
+const fn mix_const(block: Block) -> Block {
+    let mut buf: Block = [0; 16];
+    let mut c: usize = 0;
+    while c < 4 {
+        let col: [u8; 4] = word::mix_packed(u32::from_le_bytes([
+            block[4 * c], block[4 * c + 1], block[4 * c + 2], block[4 * c + 3]
+        ])).to_le_bytes();
+        buf[4 * c] = col[0];
+        buf[4 * c + 1] = col[1];
+        buf[4 * c + 2] = col[2];
+        buf[4 * c + 3] = col[3];
+        c += 1;
+    }
+    buf
+}
+
+const fn add_key_const(mut block: Block, skey: &Block) -> Block {
+    let mut idx: usize = 0;
+    while idx < 16 {
+        block[idx] ^= skey[idx];
+        idx += 1;
+    }
+    block
+}
+
+// column c after the shift and the substitution, packed as word::Word::mul_column takes it.
+#[inline(always)]
+fn gather<T>(buf: &Block, shift: &[usize; 16], sbox: &T, c: usize) -> u32
+where
+    T: ops::Index<byte::Byte, Output=byte::Byte>
+{
+    let sub: _ = |r: usize| sbox[byte::Byte::new(buf[shift[4 * c + r]])].as_inner();
+    u32::from_le_bytes([sub(0), sub(1), sub(2), sub(3)])
+}
+
+#[inline(always)]
+fn column(block: &Block, c: usize) -> u32 {
+    u32::from_le_bytes([block[4 * c], block[4 * c + 1], block[4 * c + 2], block[4 * c + 3]])
+}
+
+#[cfg(test)]
+fn mix_columns(block: &mut Block, poly: &word::Word) {
+    for c in 0..4 {
+        let packed: _ = poly.mul_column(column(block, c));
+        block[4 * c..4 * c + 4].copy_from_slice(&packed.to_le_bytes())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::primitives::state;
+
+    // the tests below write the state out row by row.
+    fn to_block(state: state::State) -> Block {
+        state.transpose().into()
+    }
+
+    fn from_block(block: Block) -> state::State {
+        state::State::from(block).transpose()
+    }
 
     #[test]
     fn test_mix_columns() {
@@ -139,8 +214,9 @@ mod tests {
             0x46, 0xe7, 0x4a, 0xc3,
             0xa6, 0x8c, 0xd8, 0x95
         ]);
-        let mut rf: _ = Round::new(initial);
-        let test: _ = rf.mix();
+        let sbox: _ = sbox::SubBox::default();
+        let mut rf: _ = Round::new(to_block(initial), &sbox);
+        let test: _ = from_block(rf.mix().block);
         let exp: _ = state::State::from([
             0x47, 0x40, 0xa3, 0x4c,
             0x37, 0xd4, 0x70, 0x9f,
@@ -149,7 +225,40 @@ mod tests {
         ]);
 
 
-        assert_eq!(test.state, exp)
+        assert_eq!(test, exp)
+    }
+
+    #[test]
+    fn test_inverse_mix_columns() {
+        // the matrix path of InvMixColumns undoes the polynomial path of MixColumns.
+        let initial: _ = state::State::from([
+            0x87, 0xf2, 0x4d, 0x97,
+            0x6e, 0x4c, 0x90, 0xec,
+            0x46, 0xe7, 0x4a, 0xc3,
+            0xa6, 0x8c, 0xd8, 0x95
+        ]);
+        let sbox: _ = sbox::SubBox::default();
+        let mut rf: _ = Round::new(to_block(initial), &sbox);
+        let mut test: _ = from_block(rf.mix().block);
+        test.mix(state::State::from([
+            0xe, 0xb, 0xd, 0x9,
+            0x9, 0xe, 0xb, 0xd,
+            0xd, 0x9, 0xe, 0xb,
+            0xb, 0xd, 0x9, 0xe
+        ]));
+        assert_eq!(initial, test);
+
+        let rsbox: _ = sbox::ReverseSubBox::default();
+        let mut rrf: _ = ReverseRound::new(to_block(test), &rsbox);
+        rrf.mix();
+        let mut exp: _ = initial;
+        exp.mix(state::State::from([
+            0xe, 0xb, 0xd, 0x9,
+            0x9, 0xe, 0xb, 0xd,
+            0xd, 0x9, 0xe, 0xb,
+            0xb, 0xd, 0x9, 0xe
+        ]));
+        assert_eq!(exp, from_block(rrf.block));
     }
 
     #[test]
@@ -160,8 +269,9 @@ mod tests {
             0x34, 0x25, 0x17, 0x55,
             0xae, 0xb6, 0x4e, 0x88
         ]);
-        let mut rf: _ = Round::new(initial);
-        let test: _ = rf.sub_and_shift();
+        let sbox: _ = sbox::SubBox::default();
+        let mut rf: _ = Round::new(to_block(initial), &sbox);
+        let test: _ = from_block(rf.sub_and_shift().block);
         let exp: _ = state::State::from([
             0xab, 0x8b, 0x89, 0x35,
             0x40, 0x7f, 0xf1, 0x05,
@@ -169,6 +279,6 @@ mod tests {
             0xc4, 0xe4, 0x4e, 0x2f
         ]);
 
-        assert_eq!(test.state, exp)
+        assert_eq!(test, exp)
     }
 }
@@ -1,20 +1,20 @@
-use crate::primitives::{sbox,state,byte,word};
+use crate::{primitives::{sbox,state,byte,word}, Block};
 
 pub struct KeySchedule {
     key: state::State,
     sbox: sbox::SubBox,
-    rcon: RCON
+    rcon: Rcon
 }
 
 impl KeySchedule {
 
-    const TMP_COL: usize = 3;
+    const TMP_WORD: usize = 3;
 
     pub fn new(key: state::State) -> Self {
         KeySchedule {
             key,
             sbox: sbox::SubBox::default(),
-            rcon: RCON::default()
+            rcon: Rcon::default()
         }
     }
 
@@ -24,9 +24,35 @@ impl KeySchedule {
         &self.key
     }
 
+    // FIPS-197 KeyExpansion on the bytes of the key, for const context where the words and
+    // their traits are not available. Word i of a round key is word i of the one before xor
+    // word i - 1, with SubWord(RotWord(w)) ^ Rcon in place of w for the first word.
+    pub const fn expand(key: Block) -> [Block; 11] {
+        let mut keys: [Block; 11] = [key; 11];
+        let mut rcon: byte::Byte = byte::Byte::new(1);
+        let mut round: usize = 1;
+        while round < 11 {
+            let prev: Block = keys[round - 1];
+            let mut next: Block = [0; 16];
+            let mut idx: usize = 0;
+            while idx < 16 {
+                next[idx] = prev[idx] ^ match idx {
+                    0 => sbox::SUB_BOX[prev[13] as usize] ^ rcon.as_inner(),
+                    1..=3 => sbox::SUB_BOX[prev[12 + (idx + 1) % 4] as usize],
+                    _ => next[idx - 4],
+                };
+                idx += 1;
+            }
+            keys[round] = next;
+            rcon = rcon.xtime();
+            round += 1;
+        }
+        keys
+    }
+
     fn tmp(&mut self) -> word::Word {
         let mut tmp: word::Word = self.key
-            .col(Self::TMP_COL)
+            .row(Self::TMP_WORD)
             .substitute(&self.sbox)
             .rotate();
         tmp[0] = tmp[0] ^ self.rcon.as_inner();
@@ -36,50 +62,48 @@ impl KeySchedule {
 
     fn chain_xor(&mut self, tmp: word::Word) -> state::State {
         let mut buf: state::State = Default::default();
-        let first_subkey: _ = std::mem::replace(&mut self.key[0], word::Word::default());
+        let first_subkey: _ = core::mem::take(&mut self.key[0]);
         buf[0] = tmp ^ first_subkey;
 
         for idx in 1..4 {
-            let prev_subkey: _ = std::mem::replace(&mut self.key[idx], word::Word::default());
-            buf[idx] = buf[idx-1].clone() ^ prev_subkey
+            let prev_subkey: _ = core::mem::take(&mut self.key[idx]);
+            buf[idx] = buf[idx-1] ^ prev_subkey
         }
 
         buf
     }
 }
 
+// the forward round keys, handed out from the last. left counts the ones not yet taken.
 pub struct ReverseKeySchedule {
-    keys: Vec<state::State>,
-    counter: usize
+    keys: [state::State; 11],
+    left: usize
 }
 
 impl ReverseKeySchedule {
     pub fn new(key: state::State) -> Self {
         let mut ksf: _ = KeySchedule::new(key);
-        let mut keys: Vec<state::State> = Vec::new();
-        for _ in 0..9 {
-            keys.push(ksf.next().clone())
+        let mut keys: [state::State; 11] = [key; 11];
+        for skey in keys[1..].iter_mut() {
+            *skey = *ksf.next()
         }
-        keys.reverse();
 
-        Self {
-            keys,
-            counter: 9
-        }
+        Self { keys, left: 11 }
     }
 
     pub fn next(&mut self) -> state::State {
-        let key: _ = self.keys.remove(self.counter);
-        self.counter -= 1;
-        key
+        self.left = self.left
+            .checked_sub(1)
+            .expect("attempting to take a round key from an exhausted schedule: there are 11 round keys.");
+        self.keys[self.left]
     }
 }
 
-struct RCON(byte::Byte);
+struct Rcon(byte::Byte);
 
-impl RCON {
+impl Rcon {
     fn update(&mut self) -> &Self {
-        let RCON(inner) = self;
+        let Rcon(inner) = self;
         *inner = *inner * byte::Byte::from(2);
         self
     }
@@ -89,9 +113,9 @@ impl RCON {
     }
 }
 
-impl Default for RCON {
+impl Default for Rcon {
     fn default() -> Self {
-        RCON(byte::Byte::from(1))   
+        Rcon(byte::Byte::from(1))   
     }
 }
 
@@ -99,15 +123,79 @@ impl Default for RCON {
 mod test {
     use super::*;
 
-    #[ignore]
+    // FIPS-197 Appendix A.1
+    fn test_key() -> state::State {
+        state::State::from([
+            0x2b, 0x7e, 0x15, 0x16,
+            0x28, 0xae, 0xd2, 0xa6,
+            0xab, 0xf7, 0x15, 0x88,
+            0x09, 0xcf, 0x4f, 0x3c
+        ])
+    }
+
     #[test]
     fn test_key_schedule() {
-        unimplemented!()
+        let mut ksf: _ = KeySchedule::new(test_key());
+
+        let first: _ = *ksf.next();
+        let exp_first: _ = state::State::from([
+            0xa0, 0xfa, 0xfe, 0x17,
+            0x88, 0x54, 0x2c, 0xb1,
+            0x23, 0xa3, 0x39, 0x39,
+            0x2a, 0x6c, 0x76, 0x05
+        ]);
+        assert_eq!(exp_first, first);
+
+        for _ in 1..9 {
+            ksf.next();
+        }
+        let exp_last: _ = state::State::from([
+            0xd0, 0x14, 0xf9, 0xa8,
+            0xc9, 0xee, 0x25, 0x89,
+            0xe1, 0x3f, 0x0c, 0xc8,
+            0xb6, 0x63, 0x0c, 0xa6
+        ]);
+        assert_eq!(&exp_last, ksf.next());
+    }
+
+    #[test]
+    fn test_expand() {
+        const KEYS: [Block; 11] = KeySchedule::expand([
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6,
+            0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c
+        ]);
+        let mut ksf: _ = KeySchedule::new(test_key());
+        assert_eq!(Block::from(test_key()), KEYS[0]);
+        for skey in KEYS[1..].iter() {
+            assert_eq!(Block::from(*ksf.next()), *skey)
+        }
+
+        for seed in 0..32u8 {
+            let key: Block = [seed.wrapping_mul(0x9d); 16];
+            let mut ksf: _ = KeySchedule::new(state::State::from(key));
+            for skey in KeySchedule::expand(key)[1..].iter() {
+                assert_eq!(Block::from(*ksf.next()), *skey)
+            }
+        }
+    }
+
+    #[test]
+    fn test_reverse_key_schedule() {
+        let mut ksf: _ = KeySchedule::new(test_key());
+        let mut exp: Vec<state::State> = vec![test_key()];
+        for _ in 0..10 {
+            exp.push(*ksf.next())
+        }
+
+        let mut rksf: _ = ReverseKeySchedule::new(test_key());
+        for key in exp.into_iter().rev() {
+            assert_eq!(key, rksf.next())
+        }
     }
 
     #[test]
     fn test_rcon_update() {
-        let mut rcon: _ = RCON::default();
+        let mut rcon: _ = Rcon::default();
         rcon.update();
         rcon.update();
         rcon.update();
@@ -117,7 +205,7 @@ mod test {
 
     #[test]
     fn test_rcon_xor() {
-        let rcon: _ = RCON::default();
+        let rcon: _ = Rcon::default();
         let mut test: _ = word::Word::from([0xd2, 0x85, 0x46, 0x79]);
         test[0] = test[0] ^ rcon.as_inner();
 
@@ -0,0 +1,60 @@
+use core::{error, fmt};
+#[cfg(feature = "std")]
+use std::io;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    // the input is not a whole number of blocks.
+    UnalignedInput(usize),
+    // the input is shorter than the mode requires.
+    InputTooShort { len: usize, min: usize },
+    // the input is longer than the construction accepts.
+    InputTooLong { len: usize, max: usize },
+    // more output was requested than the construction can produce.
+    OutputTooLong { len: usize, max: usize },
+    // a parameter is outside the range the construction allows.
+    InvalidParameter(&'static str),
+    // the entropy source could not supply the requested bytes.
+    EntropyFailure,
+    // the padding of a decrypted message is malformed.
+    InvalidPadding,
+    // reading or writing a file failed.
+    #[cfg(feature = "std")]
+    Io(io::ErrorKind),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnalignedInput(len) => write!(f, "input of {} bytes is not a multiple of the block size", len),
+            Error::InputTooShort { len, min } => write!(f, "input of {} bytes is shorter than the required {} bytes", len, min),
+            Error::InputTooLong { len, max } => write!(f, "input of {} bytes is longer than the maximum of {} bytes", len, max),
+            Error::OutputTooLong { len, max } => write!(f, "output of {} bytes is longer than the maximum of {} bytes", len, max),
+            Error::InvalidParameter(reason) => write!(f, "invalid parameter: {}", reason),
+            Error::EntropyFailure => write!(f, "the entropy source failed"),
+            Error::InvalidPadding => write!(f, "the padding is malformed"),
+            #[cfg(feature = "std")]
+            Error::Io(kind) => write!(f, "i/o failure: {}", kind),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err.kind())
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            #[cfg(feature = "std")]
+            Error::Io(kind) => io::Error::from(kind),
+            _ => io::Error::new(io::ErrorKind::InvalidData, err)
+        }
+    }
+}
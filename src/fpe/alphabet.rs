@@ -0,0 +1,74 @@
+use crate::{fpe::numeral, Error};
+
+// maps characters to numerals, so that strings can pass through FF1 and FF3-1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alphabet {
+    chars: Vec<char>,
+}
+
+impl Alphabet {
+    pub fn new(chars: &str) -> Result<Self, Error> {
+        let chars: Vec<char> = chars.chars().collect();
+        numeral::check_radix(chars.len() as u32)?;
+        for (idx, c) in chars.iter().enumerate() {
+            if chars[..idx].contains(c) {
+                return Err(Error::InvalidParameter("an alphabet may not repeat a character"))
+            }
+        }
+        Ok(Self { chars })
+    }
+
+    pub fn decimal() -> Self {
+        Self { chars: ('0'..='9').collect() }
+    }
+
+    // digits then lower case letters, the radix 36 alphabet of the NIST samples.
+    pub fn alphanumeric() -> Self {
+        Self { chars: ('0'..='9').chain('a'..='z').collect() }
+    }
+
+    pub fn radix(&self) -> u32 {
+        self.chars.len() as u32
+    }
+
+    pub fn to_numerals(&self, text: &str) -> Result<Vec<u16>, Error> {
+        text.chars()
+            .map(|c| self.chars.iter()
+                .position(|&val| val == c)
+                .map(|idx| idx as u16)
+                .ok_or(Error::InvalidParameter("a character is not in the alphabet"))
+            )
+            .collect()
+    }
+
+    pub fn from_numerals(&self, x: &[u16]) -> Result<String, Error> {
+        x.iter()
+            .map(|&val| self.chars.get(val as usize)
+                .copied()
+                .ok_or(Error::InvalidParameter("a numeral is out of range for the radix"))
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let alphabet: _ = Alphabet::alphanumeric();
+        let x: _ = alphabet.to_numerals("0123456789abcdefghi").unwrap();
+        assert_eq!((0..19).collect::<Vec<u16>>(), x);
+        assert_eq!("0123456789abcdefghi", alphabet.from_numerals(&x).unwrap());
+        assert_eq!(10, Alphabet::decimal().radix());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Alphabet::new("a").is_err());
+        assert!(Alphabet::new("abca").is_err());
+        assert!(Alphabet::decimal().to_numerals("12a4").is_err());
+        assert!(Alphabet::decimal().from_numerals(&[10]).is_err());
+    }
+}
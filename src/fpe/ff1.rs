@@ -0,0 +1,150 @@
+//! FF1 from SP 800-38G, a ten round Feistel network whose round function is a CBC-MAC
+//! over the tweak and half of the numeral string, expanded in counter mode as needed.
+
+use crate::{fpe::numeral, mac, xor_block, Block, BLOCK_SIZE, Error, RjindaelCipher};
+
+const ROUNDS: u8 = 10;
+const MAX_LEN: usize = u32::MAX as usize;
+
+#[derive(Debug, Clone)]
+pub struct Ff1 {
+    cipher: RjindaelCipher,
+    radix: u32,
+    min_len: usize,
+}
+
+impl Ff1 {
+    pub fn new(key: Block, radix: u32) -> Result<Self, Error> {
+        numeral::check_radix(radix)?;
+        Ok(Self {
+            cipher: RjindaelCipher::new(key),
+            radix,
+            min_len: numeral::min_len(radix)
+        })
+    }
+
+    pub fn encrypt(&self, tweak: &[u8], x: &mut [u16]) -> Result<(), Error> {
+        numeral::check(x, self.radix, self.min_len, MAX_LEN)?;
+        let u: _ = x.len() / 2;
+        let (mut a, mut b): _ = (x[..u].to_vec(), x[u..].to_vec());
+        let byte_len: _ = numeral::byte_len(self.radix, x.len() - u);
+
+        for i in 0..ROUNDS {
+            let y: _ = self.round(tweak, x.len(), byte_len, i, &b);
+            let c: _ = numeral::add_mod(&a, &y[y.len() - a.len()..], self.radix);
+            a = std::mem::replace(&mut b, c);
+        }
+        x[..u].copy_from_slice(&a);
+        x[u..].copy_from_slice(&b);
+        Ok(())
+    }
+
+    pub fn decrypt(&self, tweak: &[u8], x: &mut [u16]) -> Result<(), Error> {
+        numeral::check(x, self.radix, self.min_len, MAX_LEN)?;
+        let u: _ = x.len() / 2;
+        let (mut a, mut b): _ = (x[..u].to_vec(), x[u..].to_vec());
+        let byte_len: _ = numeral::byte_len(self.radix, x.len() - u);
+
+        for i in (0..ROUNDS).rev() {
+            let y: _ = self.round(tweak, x.len(), byte_len, i, &a);
+            let c: _ = numeral::sub_mod(&b, &y[y.len() - b.len()..], self.radix);
+            b = std::mem::replace(&mut a, c);
+        }
+        x[..u].copy_from_slice(&a);
+        x[u..].copy_from_slice(&b);
+        Ok(())
+    }
+
+    // steps 6.i to 6.iv, returning y as a numeral string covering both half lengths. b is
+    // the byte length of step 3, which is the same for every round.
+    fn round(&self, tweak: &[u8], n: usize, b: usize, i: u8, half: &[u16]) -> Vec<u16> {
+        let u: _ = n / 2;
+        let v: _ = n - u;
+        let d: _ = 4 * b.div_ceil(4) + 4;
+
+        let mut pq: Vec<u8> = vec![1, 2, 1];
+        pq.extend_from_slice(&self.radix.to_be_bytes()[1..]);
+        pq.extend_from_slice(&[10, u as u8]);
+        pq.extend_from_slice(&(n as u32).to_be_bytes());
+        pq.extend_from_slice(&(tweak.len() as u32).to_be_bytes());
+        pq.extend_from_slice(tweak);
+        pq.resize(pq.len() + (BLOCK_SIZE - (tweak.len() + b + 1) % BLOCK_SIZE) % BLOCK_SIZE, 0);
+        pq.push(i);
+        pq.extend_from_slice(&numeral::to_bytes(half, self.radix, b));
+
+        let r: Block = mac::cbc::chain(&self.cipher, [0; BLOCK_SIZE], &pq);
+        let mut s: Vec<u8> = r.to_vec();
+        for j in 1..d.div_ceil(BLOCK_SIZE) {
+            let mut block: Block = (j as u128).to_be_bytes();
+            xor_block(&mut block, &r);
+            self.cipher.encrypt_block(&mut block);
+            s.extend_from_slice(&block);
+        }
+        numeral::from_bytes(&s[..d], self.radix, v)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fpe::Alphabet;
+    use crate::test_util::unhex;
+
+    fn key() -> Block {
+        [
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6,
+            0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c
+        ]
+    }
+
+    // NIST FF1 samples 1 to 3, the AES-128 cases.
+    #[test]
+    fn test_nist_samples() {
+        let cases: [(u32, &str, &str, &str); 3] = [
+            (10, "", "0123456789", "2433477484"),
+            (10, "39383736353433323130", "0123456789", "6124200773"),
+            (36, "3737373770717273373737", "0123456789abcdefghi", "a9tv40mll9kdu509eum"),
+        ];
+
+        for (radix, tweak, pt, ct) in cases.iter() {
+            let alphabet: _ = match radix {
+                10 => Alphabet::decimal(),
+                _ => Alphabet::alphanumeric()
+            };
+            let ff1: _ = Ff1::new(key(), *radix).unwrap();
+            let mut x: _ = alphabet.to_numerals(pt).unwrap();
+            ff1.encrypt(&unhex(tweak), &mut x).unwrap();
+            assert_eq!(*ct, alphabet.from_numerals(&x).unwrap());
+            ff1.decrypt(&unhex(tweak), &mut x).unwrap();
+            assert_eq!(*pt, alphabet.from_numerals(&x).unwrap());
+        }
+    }
+
+    // the radix extremes, cross-checked against a reference model over pyca cryptography.
+    #[test]
+    fn test_radix_bounds() {
+        let ff1: _ = Ff1::new(key(), 1 << 16).unwrap();
+        let mut x: [u16; 5] = [0, 1, 65535, 4096, 300];
+        ff1.encrypt(b"tweak", &mut x).unwrap();
+        assert_eq!([28586, 20560, 7833, 13109, 21701], x);
+        ff1.decrypt(b"tweak", &mut x).unwrap();
+        assert_eq!([0, 1, 65535, 4096, 300], x);
+
+        let ff1: _ = Ff1::new(key(), 2).unwrap();
+        let mut x: [u16; 20] = [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1];
+        ff1.encrypt(&[], &mut x).unwrap();
+        assert_eq!([1, 1, 1, 0, 1, 1, 0, 1, 1, 1, 0, 0, 0, 1, 1, 0, 0, 1, 1, 1], x);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Ff1::new(key(), 1).is_err());
+        assert!(Ff1::new(key(), (1 << 16) + 1).is_err());
+
+        let ff1: _ = Ff1::new(key(), 10).unwrap();
+        let mut short: _ = [0; 5];
+        assert_eq!(Err(Error::InputTooShort { len: 5, min: 6 }), ff1.encrypt(&[], &mut short));
+        let mut digits: _ = [0, 1, 2, 3, 4, 10];
+        assert!(ff1.encrypt(&[], &mut digits).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! FF3-1 from SP 800-38G Rev. 1, an eight round Feistel network keyed by the byte
+//! reversed key, whose 56-bit tweak is split into two 32-bit halves per round parity.
+
+use crate::{fpe::numeral, Block, BLOCK_SIZE, Error, RjindaelCipher};
+
+pub const FF3_TWEAK_SIZE: usize = 7;
+
+const ROUNDS: u8 = 8;
+const HALF_TWEAK_SIZE: usize = 4;
+const NUM_SIZE: usize = BLOCK_SIZE - HALF_TWEAK_SIZE;
+
+#[derive(Debug, Clone)]
+pub struct Ff3 {
+    cipher: RjindaelCipher,
+    radix: u32,
+    min_len: usize,
+    max_len: usize,
+}
+
+impl Ff3 {
+    pub fn new(mut key: Block, radix: u32) -> Result<Self, Error> {
+        numeral::check_radix(radix)?;
+        key.reverse();
+
+        // 2 * floor(log_radix(2^96)), the longest string whose halves fit the 96-bit input.
+        let mut domain: u128 = radix as u128;
+        let mut half: usize = 0;
+        while domain <= 1 << 96 {
+            domain *= radix as u128;
+            half += 1;
+        }
+        Ok(Self {
+            cipher: RjindaelCipher::new(key),
+            radix,
+            min_len: numeral::min_len(radix),
+            max_len: 2 * half
+        })
+    }
+
+    pub fn encrypt(&self, tweak: &[u8; FF3_TWEAK_SIZE], x: &mut [u16]) -> Result<(), Error> {
+        self.encrypt_with(&expand(tweak), x)
+    }
+
+    pub fn decrypt(&self, tweak: &[u8; FF3_TWEAK_SIZE], x: &mut [u16]) -> Result<(), Error> {
+        self.decrypt_with(&expand(tweak), x)
+    }
+
+    // numeral strings are handled reversed throughout, so the least significant numeral leads.
+    fn encrypt_with(&self, tweak: &[u8; 8], x: &mut [u16]) -> Result<(), Error> {
+        numeral::check(x, self.radix, self.min_len, self.max_len)?;
+        let u: _ = x.len().div_ceil(2);
+        x.reverse();
+        let (mut a, mut b): _ = (x[x.len() - u..].to_vec(), x[..x.len() - u].to_vec());
+
+        for i in 0..ROUNDS {
+            let m: _ = match i % 2 { 0 => u, _ => x.len() - u };
+            let y: _ = self.round(tweak, i, &b, m);
+            let c: _ = numeral::add_mod(&a, &y, self.radix);
+            a = std::mem::replace(&mut b, c);
+        }
+        x[..b.len()].copy_from_slice(&b);
+        x[b.len()..].copy_from_slice(&a);
+        x.reverse();
+        Ok(())
+    }
+
+    fn decrypt_with(&self, tweak: &[u8; 8], x: &mut [u16]) -> Result<(), Error> {
+        numeral::check(x, self.radix, self.min_len, self.max_len)?;
+        let u: _ = x.len().div_ceil(2);
+        x.reverse();
+        let (mut a, mut b): _ = (x[x.len() - u..].to_vec(), x[..x.len() - u].to_vec());
+
+        for i in (0..ROUNDS).rev() {
+            let m: _ = match i % 2 { 0 => u, _ => x.len() - u };
+            let y: _ = self.round(tweak, i, &a, m);
+            let c: _ = numeral::sub_mod(&b, &y, self.radix);
+            b = std::mem::replace(&mut a, c);
+        }
+        x[..b.len()].copy_from_slice(&b);
+        x[b.len()..].copy_from_slice(&a);
+        x.reverse();
+        Ok(())
+    }
+
+    // steps 4.i to 4.iv, with the half already reversed.
+    fn round(&self, tweak: &[u8; 8], i: u8, half: &[u16], m: usize) -> Vec<u16> {
+        let w: _ = match i % 2 {
+            0 => &tweak[HALF_TWEAK_SIZE..],
+            _ => &tweak[..HALF_TWEAK_SIZE]
+        };
+        let mut p: Block = [0; BLOCK_SIZE];
+        p[..HALF_TWEAK_SIZE].copy_from_slice(w);
+        p[HALF_TWEAK_SIZE - 1] ^= i;
+        p[HALF_TWEAK_SIZE..].copy_from_slice(&numeral::to_bytes(half, self.radix, NUM_SIZE));
+
+        p.reverse();
+        self.cipher.encrypt_block(&mut p);
+        p.reverse();
+
+        numeral::from_bytes(&p, self.radix, m)
+    }
+}
+
+// the FF3-1 tweak T_L = T[0..28] || 0^4 and T_R = T[32..56] || T[28..32] || 0^4.
+fn expand(tweak: &[u8; FF3_TWEAK_SIZE]) -> [u8; 8] {
+    [
+        tweak[0], tweak[1], tweak[2], tweak[3] & 0xf0,
+        tweak[4], tweak[5], tweak[6], tweak[3] << 4
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fpe::Alphabet;
+    use crate::test_util::unhex;
+
+    fn key() -> Block {
+        [
+            0xef, 0x43, 0x59, 0xd8, 0xd5, 0x80, 0xaa, 0x4f,
+            0x7f, 0x03, 0x6d, 0x6f, 0x04, 0xfc, 0x6a, 0x94
+        ]
+    }
+
+    fn tweak(hex: &str) -> [u8; FF3_TWEAK_SIZE] {
+        let mut buf: [u8; FF3_TWEAK_SIZE] = [0; FF3_TWEAK_SIZE];
+        buf.copy_from_slice(&unhex(hex));
+        buf
+    }
+
+    // NIST FF3 samples 1 and 2, run with the original 64-bit tweak to check the rounds.
+    #[test]
+    fn test_ff3_samples() {
+        let ff3: _ = Ff3::new(key(), 10).unwrap();
+        let cases: [(&str, &str); 2] = [
+            ("d8e7920afa330a73", "750918814058654607"),
+            ("9a768a92f60e12d8", "018989839189395384"),
+        ];
+        for (hex, ct) in cases.iter() {
+            let mut t: [u8; 8] = [0; 8];
+            t.copy_from_slice(&unhex(hex));
+            let mut x: _ = Alphabet::decimal().to_numerals("890121234567890000").unwrap();
+            ff3.encrypt_with(&t, &mut x).unwrap();
+            assert_eq!(*ct, Alphabet::decimal().from_numerals(&x).unwrap());
+            ff3.decrypt_with(&t, &mut x).unwrap();
+            assert_eq!("890121234567890000", Alphabet::decimal().from_numerals(&x).unwrap());
+        }
+    }
+
+    // FF3-1 with 56-bit tweaks, cross-checked against a reference model over pyca cryptography.
+    #[test]
+    fn test_ff3_1() {
+        let cases: [(Alphabet, &str, &str, &str); 3] = [
+            (Alphabet::decimal(), "d8e7920afa330a", "890121234567890000", "477064185124354662"),
+            (Alphabet::decimal(), "d8e7920afa330a", "4000001234567899", "2262958375055317"),
+            (Alphabet::alphanumeric(), "9a768a92f60e12", "0123456789abcdefghi", "c5w89fqt1cmo9t608nc"),
+        ];
+        for (alphabet, hex, pt, ct) in cases.iter() {
+            let ff3: _ = Ff3::new(key(), alphabet.radix()).unwrap();
+            let mut x: _ = alphabet.to_numerals(pt).unwrap();
+            ff3.encrypt(&tweak(hex), &mut x).unwrap();
+            assert_eq!(*ct, alphabet.from_numerals(&x).unwrap());
+            ff3.decrypt(&tweak(hex), &mut x).unwrap();
+            assert_eq!(*pt, alphabet.from_numerals(&x).unwrap());
+        }
+
+        let ff3: _ = Ff3::new(key(), 1 << 16).unwrap();
+        let mut x: [u16; 5] = [0, 1, 65535, 4096, 300];
+        ff3.encrypt(&tweak("d8e7920afa330a"), &mut x).unwrap();
+        assert_eq!([21453, 25406, 27504, 16482, 31621], x);
+    }
+
+    #[test]
+    fn test_lengths() {
+        let ff3: _ = Ff3::new(key(), 10).unwrap();
+        assert_eq!((6, 56), (ff3.min_len, ff3.max_len));
+        let mut long: _ = [0; 57];
+        assert_eq!(Err(Error::InputTooLong { len: 57, max: 56 }), ff3.encrypt(&[0; 7], &mut long));
+        assert_eq!(12, Ff3::new(key(), 1 << 16).unwrap().max_len);
+    }
+}
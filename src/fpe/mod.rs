@@ -0,0 +1,8 @@
+mod alphabet;
+mod ff1;
+mod ff3;
+mod numeral;
+
+pub use alphabet::Alphabet;
+pub use ff1::Ff1;
+pub use ff3::{Ff3, FF3_TWEAK_SIZE};
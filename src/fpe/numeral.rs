@@ -0,0 +1,136 @@
+//! Arithmetic on numeral strings, most significant numeral first, without a bignum type.
+//! Numbers only ever move between a radix and big-endian bytes, so schoolbook carries suffice.
+
+use crate::Error;
+
+pub(crate) const MIN_RADIX: u32 = 2;
+pub(crate) const MAX_RADIX: u32 = 1 << 16;
+
+// SP 800-38G requires radix^minlen to be at least one million.
+const MIN_DOMAIN: u64 = 1_000_000;
+
+pub(crate) fn check_radix(radix: u32) -> Result<(), Error> {
+    match (MIN_RADIX..=MAX_RADIX).contains(&radix) {
+        true => Ok(()),
+        false => Err(Error::InvalidParameter("the radix must lie in 2..=65536"))
+    }
+}
+
+pub(crate) fn min_len(radix: u32) -> usize {
+    let mut domain: u64 = radix as u64;
+    let mut len: usize = 1;
+    while domain < MIN_DOMAIN {
+        domain *= radix as u64;
+        len += 1;
+    }
+    len.max(2)
+}
+
+pub(crate) fn check(x: &[u16], radix: u32, min: usize, max: usize) -> Result<(), Error> {
+    if x.len() < min {
+        return Err(Error::InputTooShort { len: x.len(), min })
+    }
+    if x.len() > max {
+        return Err(Error::InputTooLong { len: x.len(), max })
+    }
+    match x.iter().all(|&val| (val as u32) < radix) {
+        true => Ok(()),
+        false => Err(Error::InvalidParameter("a numeral is out of range for the radix"))
+    }
+}
+
+// NUM_radix(x) as len big-endian bytes, dropping anything above.
+pub(crate) fn to_bytes(x: &[u16], radix: u32, len: usize) -> Vec<u8> {
+    let mut buf: Vec<u8> = vec![0; len];
+    for &val in x.iter() {
+        let mut carry: u64 = val as u64;
+        for byte in buf.iter_mut().rev() {
+            let acc: _ = *byte as u64 * radix as u64 + carry;
+            *byte = acc as u8;
+            carry = acc >> 8;
+        }
+    }
+    buf
+}
+
+// STR_radix^m of a big-endian number reduced mod radix^m.
+pub(crate) fn from_bytes(bytes: &[u8], radix: u32, m: usize) -> Vec<u16> {
+    let mut num: _ = bytes.to_vec();
+    let mut buf: Vec<u16> = vec![0; m];
+    for val in buf.iter_mut().rev() {
+        let mut rem: u64 = 0;
+        for byte in num.iter_mut() {
+            let acc: _ = (rem << 8) | *byte as u64;
+            *byte = (acc / radix as u64) as u8;
+            rem = acc % radix as u64;
+        }
+        *val = rem as u16;
+    }
+    buf
+}
+
+// the number of bytes in radix^len - 1, i.e. ceil(ceil(len * log2(radix)) / 8).
+pub(crate) fn byte_len(radix: u32, len: usize) -> usize {
+    let max: _ = vec![(radix - 1) as u16; len];
+    let bytes: _ = to_bytes(&max, radix, 2 * len + 1);
+    bytes.len() - bytes.iter().take_while(|&&byte| byte == 0).count()
+}
+
+pub(crate) fn add_mod(lhs: &[u16], rhs: &[u16], radix: u32) -> Vec<u16> {
+    let mut buf: Vec<u16> = vec![0; lhs.len()];
+    let mut carry: u32 = 0;
+    for ((val, &l), &r) in buf.iter_mut().zip(lhs.iter()).zip(rhs.iter()).rev() {
+        let sum: _ = l as u32 + r as u32 + carry;
+        carry = (sum >= radix) as u32;
+        *val = (sum - carry * radix) as u16;
+    }
+    buf
+}
+
+pub(crate) fn sub_mod(lhs: &[u16], rhs: &[u16], radix: u32) -> Vec<u16> {
+    let mut buf: Vec<u16> = vec![0; lhs.len()];
+    let mut borrow: u32 = 0;
+    for ((val, &l), &r) in buf.iter_mut().zip(lhs.iter()).zip(rhs.iter()).rev() {
+        let sub: _ = r as u32 + borrow;
+        borrow = (sub > l as u32) as u32;
+        *val = (l as u32 + borrow * radix - sub) as u16;
+    }
+    buf
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bytes() {
+        let x: [u16; 4] = [1, 2, 3, 4];
+        assert_eq!(vec![0x04, 0xd2], to_bytes(&x, 10, 2));
+        assert_eq!(vec![1, 2, 3, 4], from_bytes(&[0x04, 0xd2], 10, 4));
+        // reduction keeps the low numerals.
+        assert_eq!(vec![3, 4], from_bytes(&[0x04, 0xd2], 10, 2));
+        assert_eq!(vec![0xffff, 0x0001], from_bytes(&to_bytes(&[0xffff, 1], 1 << 16, 4), 1 << 16, 2));
+    }
+
+    #[test]
+    fn test_byte_len() {
+        assert_eq!(1, byte_len(2, 8));
+        assert_eq!(2, byte_len(2, 9));
+        assert_eq!(3, byte_len(10, 5));
+        assert_eq!(4, byte_len(1 << 16, 2));
+    }
+
+    #[test]
+    fn test_mod_arithmetic() {
+        assert_eq!(vec![0, 1], add_mod(&[9, 9], &[0, 2], 10));
+        assert_eq!(vec![9, 9], sub_mod(&[0, 1], &[0, 2], 10));
+        assert_eq!(vec![1, 2, 3], sub_mod(&add_mod(&[1, 2, 3], &[7, 7, 7], 10), &[7, 7, 7], 10));
+    }
+
+    #[test]
+    fn test_min_len() {
+        assert_eq!(6, min_len(10));
+        assert_eq!(20, min_len(2));
+        assert_eq!(2, min_len(1 << 16));
+    }
+}
@@ -0,0 +1,72 @@
+//! The single-block-length compression functions of Preneel, Govaerts and Vandewalle.
+//! Each call keys the cipher afresh, so hashing runs the key schedule once per block.
+
+use crate::{xor_block, Block, RjindaelCipher};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    // E_m(h) ^ h, keyed by the message block.
+    DaviesMeyer,
+    // E_h(m) ^ m, keyed by the chaining value.
+    MatyasMeyerOseas,
+    // E_h(m) ^ m ^ h, keyed by the chaining value.
+    MiyaguchiPreneel,
+}
+
+impl Compression {
+    pub fn compress(self, chain: &Block, block: &Block) -> Block {
+        match self {
+            Compression::DaviesMeyer => {
+                let mut buf: Block = *chain;
+                RjindaelCipher::new(*block).encrypt_block(&mut buf);
+                xor_block(&mut buf, chain);
+                buf
+            },
+            Compression::MatyasMeyerOseas => {
+                let mut buf: Block = *block;
+                RjindaelCipher::new(*chain).encrypt_block(&mut buf);
+                xor_block(&mut buf, block);
+                buf
+            },
+            Compression::MiyaguchiPreneel => {
+                let mut buf: Block = Compression::MatyasMeyerOseas.compress(chain, block);
+                xor_block(&mut buf, chain);
+                buf
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compress() {
+        let chain: Block = *b"chaining value 0";
+        let block: Block = *b"message block 01";
+
+        let mut dm: Block = chain;
+        RjindaelCipher::new(block).encrypt_block(&mut dm);
+        xor_block(&mut dm, &chain);
+        assert_eq!(dm, Compression::DaviesMeyer.compress(&chain, &block));
+
+        let mut mmo: Block = block;
+        RjindaelCipher::new(chain).encrypt_block(&mut mmo);
+        xor_block(&mut mmo, &block);
+        assert_eq!(mmo, Compression::MatyasMeyerOseas.compress(&chain, &block));
+
+        xor_block(&mut mmo, &chain);
+        assert_eq!(mmo, Compression::MiyaguchiPreneel.compress(&chain, &block));
+    }
+
+    #[test]
+    fn test_zero_chain() {
+        // with a zero chaining value Miyaguchi-Preneel reduces to Matyas-Meyer-Oseas.
+        let block: Block = *b"message block 01";
+        assert_eq!(
+            Compression::MatyasMeyerOseas.compress(&[0; 16], &block),
+            Compression::MiyaguchiPreneel.compress(&[0; 16], &block)
+        );
+    }
+}
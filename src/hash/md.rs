@@ -0,0 +1,90 @@
+//! Merkle-Damgard iteration of a compression function, strengthened by a final
+//! 64-bit big-endian bit length after the 0x80 padding byte.
+
+use crate::{hash, to_block, Block, BLOCK_SIZE};
+
+const LENGTH_SIZE: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct BlockHash {
+    compression: hash::Compression,
+    iv: Block,
+}
+
+impl BlockHash {
+    // starts from the all zero chaining value.
+    pub fn new(compression: hash::Compression) -> Self {
+        Self { compression, iv: [0; BLOCK_SIZE] }
+    }
+
+    pub fn iv(mut self, iv: Block) -> Self {
+        self.iv = iv;
+        self
+    }
+
+    pub fn hash(&self, data: &[u8]) -> Block {
+        let mut padded: _ = data.to_vec();
+        padded.push(0x80);
+        padded.resize((padded.len() + LENGTH_SIZE).div_ceil(BLOCK_SIZE) * BLOCK_SIZE - LENGTH_SIZE, 0);
+        padded.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+        iterate(self.compression, self.iv, &padded)
+    }
+}
+
+pub(crate) fn iterate(compression: hash::Compression, iv: Block, padded: &[u8]) -> Block {
+    padded.chunks(BLOCK_SIZE)
+        .fold(iv, |chain, chunk| compression.compress(&chain, &to_block(chunk)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::unhex;
+
+    // cross-checked against a reference model over pyca cryptography.
+    #[test]
+    fn test_hash() {
+        let msgs: [Vec<u8>; 3] = [Vec::new(), b"abc".to_vec(), (0..40).collect()];
+        let cases: [(hash::Compression, [&str; 3]); 3] = [
+            (hash::Compression::DaviesMeyer, [
+                "0edd33d3c621e546455bd8ba1418bec8",
+                "10d540f6e1d7d2b09b47a65e6de29300",
+                "ea30af096c909dc0c4052e04e9e19442"
+            ]),
+            (hash::Compression::MatyasMeyerOseas, [
+                "bad78e726c1ec02b7ebfe92b23d9ec34",
+                "bd2f2ebd93fadc48bc00174d95422741",
+                "0f41fd8b56e89360ce41332489197210"
+            ]),
+            (hash::Compression::MiyaguchiPreneel, [
+                "bad78e726c1ec02b7ebfe92b23d9ec34",
+                "bd2f2ebd93fadc48bc00174d95422741",
+                "8a4e5aee2fd9047068a805f5a8f71f66"
+            ]),
+        ];
+
+        for (compression, exps) in cases.iter() {
+            let hasher: _ = BlockHash::new(*compression);
+            for (msg, exp) in msgs.iter().zip(exps.iter()) {
+                assert_eq!(unhex(exp), hasher.hash(msg).to_vec());
+            }
+        }
+    }
+
+    #[test]
+    fn test_iv() {
+        let hasher: _ = BlockHash::new(hash::Compression::DaviesMeyer);
+        let other: _ = hasher.clone().iv([0x5a; 16]);
+        assert_ne!(hasher.hash(b"abc"), other.hash(b"abc"));
+    }
+
+    #[test]
+    fn test_strengthening() {
+        // the length block separates a message from its padded form.
+        let hasher: _ = BlockHash::new(hash::Compression::MatyasMeyerOseas);
+        let mut padded: Vec<u8> = b"abc".to_vec();
+        padded.push(0x80);
+        assert_ne!(hasher.hash(b"abc"), hasher.hash(&padded));
+        assert_ne!(hasher.hash(&[]), hasher.hash(&[0]));
+    }
+}
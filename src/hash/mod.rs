@@ -0,0 +1,7 @@
+mod compression;
+pub(crate) mod md;
+mod zigbee;
+
+pub use compression::Compression;
+pub use md::BlockHash;
+pub use zigbee::ZigbeeMmo;
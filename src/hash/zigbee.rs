@@ -0,0 +1,62 @@
+//! The Zigbee AES-MMO hash from the Zigbee specification annex B.6, a Matyas-Meyer-Oseas
+//! iteration from a zero IV whose strengthening uses a 16-bit length for short messages
+//! and a 32-bit length followed by 16 zero bits otherwise.
+
+use crate::{hash, Block, BLOCK_SIZE, Error};
+
+const SHORT_LIMIT: usize = 1 << 13;
+const MAX_LEN: usize = (1 << 29) - 1;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZigbeeMmo;
+
+impl ZigbeeMmo {
+    pub fn hash(data: &[u8]) -> Result<Block, Error> {
+        if data.len() > MAX_LEN {
+            return Err(Error::InputTooLong { len: data.len(), max: MAX_LEN })
+        }
+
+        let bits: _ = data.len() as u32 * 8;
+        let trailer: Vec<u8> = match data.len() < SHORT_LIMIT {
+            true => (bits as u16).to_be_bytes().to_vec(),
+            false => [&bits.to_be_bytes()[..], &[0, 0]].concat()
+        };
+
+        let mut padded: _ = data.to_vec();
+        padded.push(0x80);
+        padded.resize((padded.len() + trailer.len()).div_ceil(BLOCK_SIZE) * BLOCK_SIZE - trailer.len(), 0);
+        padded.extend_from_slice(&trailer);
+        Ok(hash::md::iterate(hash::Compression::MatyasMeyerOseas, [0; BLOCK_SIZE], &padded))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::unhex;
+
+    #[test]
+    fn test_spec_vector() {
+        // the Zigbee specification test vector for a single byte message.
+        let exp: _ = unhex("ae3a102a28d43ee0d4a09e22788b206c");
+        assert_eq!(exp, ZigbeeMmo::hash(&[0xc0]).unwrap().to_vec());
+    }
+
+    #[test]
+    fn test_install_code() {
+        // an install code with its CRC hashes to the preconfigured link key.
+        let code: _ = unhex("83fed3407a939723a5c639b26916d505c3b5");
+        let exp: _ = unhex("66b6900981e1ee3ca4206b6b861c02bb");
+        assert_eq!(exp, ZigbeeMmo::hash(&code).unwrap().to_vec());
+    }
+
+    #[test]
+    fn test_long_form() {
+        // either side of the switch to the 32-bit length, cross-checked against a model.
+        let data: Vec<u8> = (0..SHORT_LIMIT).map(|x| x as u8).collect();
+        let short: _ = unhex("24ec2fe75bbffcb34789bc0610e7f165");
+        let long: _ = unhex("dc6b0687f09f8607131c170b3bd31591");
+        assert_eq!(short, ZigbeeMmo::hash(&data[..SHORT_LIMIT - 1]).unwrap().to_vec());
+        assert_eq!(long, ZigbeeMmo::hash(&data).unwrap().to_vec());
+    }
+}
@@ -0,0 +1,327 @@
+//! Key-based key derivation from SP 800-108 with AES-CMAC as the PRF.
+//! Each PRF call takes an iteration value, a big-endian counter and the fixed input data,
+//! where the fixed input is either supplied raw or built as Label || 0x00 || Context || [L]_2.
+//! Counter mode has no iteration value, feedback mode chains the previous output block from
+//! an IV, and double-pipeline mode chains a separate pipeline A(i) = PRF(A(i-1)) from A(0) = fixed.
+
+use crate::{mac, Block, BLOCK_SIZE, Error};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Counter,
+    // the IV that stands in for the output block before the first, it may be empty.
+    Feedback(Vec<u8>),
+    DoublePipeline,
+}
+
+// where the counter sits in the PRF input, named after the CAVP CTRLOCATION values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CounterLocation {
+    // before the iteration value, in counter mode this is the same as before the fixed input.
+    BeforeIter,
+    // after the iteration value and before the fixed input.
+    BeforeFixed,
+    // after the given number of bytes of the fixed input.
+    MiddleFixed(usize),
+    AfterFixed,
+}
+
+#[derive(Debug, Clone)]
+pub struct Kbkdf {
+    prf: mac::Cmac,
+    mode: Mode,
+    counter_width: usize,
+    location: CounterLocation,
+    length_width: usize,
+}
+
+impl Kbkdf {
+    const MAX_WIDTH: usize = 4;
+
+    // defaults to a 32-bit counter before the fixed input and a 32-bit [L]_2.
+    pub fn new(key: Block, mode: Mode) -> Self {
+        Self {
+            prf: mac::Cmac::new(key),
+            mode,
+            counter_width: 4,
+            location: CounterLocation::BeforeFixed,
+            length_width: 4
+        }
+    }
+
+    // the counter width r in bytes, zero leaves the counter out of feedback and double-pipeline mode.
+    pub fn counter_width(mut self, width: usize) -> Self {
+        self.counter_width = width;
+        self
+    }
+
+    pub fn counter_location(mut self, location: CounterLocation) -> Self {
+        self.location = location;
+        self
+    }
+
+    // the width in bytes of the output bit length [L]_2 appended to label and context.
+    pub fn length_width(mut self, width: usize) -> Self {
+        self.length_width = width;
+        self
+    }
+
+    // fills the output from the fixed input Label || 0x00 || Context || [L]_2.
+    pub fn derive(&self, label: &[u8], context: &[u8], out: &mut [u8]) -> Result<(), Error> {
+        if self.length_width == 0 || self.length_width > Self::MAX_WIDTH {
+            return Err(Error::InvalidParameter("the length field must be between 1 and 4 bytes"))
+        }
+        let bits: u64 = out.len() as u64 * 8;
+        if bits > max_value(self.length_width) as u64 {
+            return Err(Error::OutputTooLong { len: out.len(), max: max_value(self.length_width) / 8 })
+        }
+
+        let mut fixed: Vec<u8> = Vec::with_capacity(label.len() + context.len() + 1 + self.length_width);
+        fixed.extend_from_slice(label);
+        fixed.push(0x00);
+        fixed.extend_from_slice(context);
+        fixed.extend_from_slice(&bits.to_be_bytes()[8 - self.length_width..]);
+        self.derive_fixed(&fixed, out)
+    }
+
+    // fills the output from opaque fixed input data, as the CAVP vectors supply it.
+    pub fn derive_fixed(&self, fixed: &[u8], out: &mut [u8]) -> Result<(), Error> {
+        self.check(fixed, out.len())?;
+
+        let mut iter: Vec<u8> = match &self.mode {
+            Mode::Counter => Vec::new(),
+            Mode::Feedback(iv) => iv.clone(),
+            Mode::DoublePipeline => fixed.to_vec()
+        };
+        for (idx, chunk) in out.chunks_mut(BLOCK_SIZE).enumerate() {
+            if self.mode == Mode::DoublePipeline {
+                iter = self.prf.mac(&iter).to_vec();
+            }
+            let block: _ = self.prf.mac(&self.prf_input(&iter, idx as u64 + 1, fixed));
+            chunk.copy_from_slice(&block[..chunk.len()]);
+            if let Mode::Feedback(_) = self.mode {
+                iter = block.to_vec();
+            }
+        }
+        Ok(())
+    }
+
+    fn check(&self, fixed: &[u8], len: usize) -> Result<(), Error> {
+        if self.counter_width > Self::MAX_WIDTH {
+            return Err(Error::InvalidParameter("the counter must be at most 4 bytes"))
+        }
+        if self.counter_width == 0 {
+            return match self.mode {
+                Mode::Counter => Err(Error::InvalidParameter("counter mode requires a counter")),
+                _ => Ok(())
+            }
+        }
+        if let CounterLocation::MiddleFixed(offset) = self.location {
+            if offset > fixed.len() {
+                return Err(Error::InvalidParameter("the counter offset is beyond the fixed input"))
+            }
+        }
+
+        // the counter must not wrap, so at most 2^r - 1 blocks are available.
+        let max_blocks: _ = max_value(self.counter_width);
+        if len.div_ceil(BLOCK_SIZE) > max_blocks {
+            return Err(Error::OutputTooLong { len, max: max_blocks * BLOCK_SIZE })
+        }
+        Ok(())
+    }
+
+    fn prf_input(&self, iter: &[u8], counter: u64, fixed: &[u8]) -> Vec<u8> {
+        let counter: _ = &counter.to_be_bytes()[8 - self.counter_width..];
+        let (head, tail): (&[u8], &[u8]) = match self.location {
+            CounterLocation::BeforeIter | CounterLocation::BeforeFixed => (&[], fixed),
+            CounterLocation::MiddleFixed(offset) => fixed.split_at(offset),
+            CounterLocation::AfterFixed => (fixed, &[]),
+        };
+
+        let mut buf: Vec<u8> = Vec::with_capacity(iter.len() + counter.len() + fixed.len());
+        if self.location == CounterLocation::BeforeIter {
+            buf.extend_from_slice(counter);
+        }
+        buf.extend_from_slice(iter);
+        buf.extend_from_slice(head);
+        if self.location != CounterLocation::BeforeIter {
+            buf.extend_from_slice(counter);
+        }
+        buf.extend_from_slice(tail);
+        buf
+    }
+}
+
+// the largest value a big-endian field of the given width in bytes can hold.
+fn max_value(width: usize) -> usize {
+    match width {
+        width if width >= 8 => usize::MAX,
+        width => ((1_u64 << (8 * width)) - 1) as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::unhex;
+
+    const KEY: Block = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+        0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f
+    ];
+
+    fn fixed() -> Vec<u8> {
+        (0x20..0x40).collect()
+    }
+
+    fn derive(kdf: &Kbkdf, fixed: &[u8], len: usize) -> Vec<u8> {
+        let mut out: _ = vec![0; len];
+        kdf.derive_fixed(fixed, &mut out).unwrap();
+        out
+    }
+
+    // KDFCTR_gen.rsp [PRF=CMAC_AES128] with L = 128, COUNT=0 of each section as
+    // (CTRLOCATION, RLEN in bytes, KI, FixedInputData, KO).
+    const CAVP_COUNTER: [(CounterLocation, usize, &str, &str, &str); 5] = [
+        (CounterLocation::BeforeFixed, 1, "dff1e50ac0b69dc40f1051d46c2b069c",
+         "c16e6e02c5a3dcc8d78b9ac1306877761310455b4e41469951d9e6c2245a064b33fd8c3b01203a7824485bf0a64060c4648b707d2607935699316ea5",
+         "8be8f0869b3c0ba97b71863d1b9f7813"),
+        (CounterLocation::BeforeFixed, 2, "30ec5f6fa1def33cff008178c4454211",
+         "c95e7b1d4f2570259abfc05bb00730f0284c3bb9a61d07259848a1cb57c81d8a6c3382c500bf801dfc8f70726b082cf4c3fa34386c1e7bf0e5471438",
+         "00018fff9574994f5c4457f461c7a67e"),
+        (CounterLocation::BeforeFixed, 3, "ca1cf43e5ccd512cc719a2f9de41734c",
+         "e3884ac963196f02ddd09fc04c20c88b60faa775b5ef6feb1faf8c5e098b5210e2b4e45d62cc0bf907fd68022ee7b15631b5c8daf903d99642c5b831",
+         "1cb2b12326cc5ec1eba248167f0efd58"),
+        (CounterLocation::BeforeFixed, 4, "c10b152e8c97b77e18704e0f0bd38305",
+         "98cd4cbbbebe15d17dc86e6dbad800a2dcbd64f7c7ad0e78e9cf94ffdba89d03e97eadf6c4f7b806caf52aa38f09d0eb71d71f497bcc6906b48d36c4",
+         "26faf61908ad9ee881b8305c221db53f"),
+        (CounterLocation::AfterFixed, 1, "e61a51e1633e7d0de704dcebbd8f962f",
+         "5eef88f8cb188e63e08e23c957ee424a3345da88400c567548b57693931a847501f8e1bce1c37a09ef8c6e2ad553dd0f603b52cc6d4e4cbb76eb6c8f",
+         "63a5647d0fe69d21fc420b1a8ce34cc1"),
+    ];
+
+    #[test]
+    fn test_cavp_counter() {
+        for (location, width, key, fixed, exp) in CAVP_COUNTER.iter() {
+            let kdf: _ = Kbkdf::new(crate::to_block(&unhex(key)), Mode::Counter)
+                .counter_location(*location)
+                .counter_width(*width);
+            assert_eq!(unhex(exp), derive(&kdf, &unhex(fixed), 16));
+
+            // a counter after none or all of the fixed input sits before or after it.
+            let fixed: _ = unhex(fixed);
+            let offset: _ = match location {
+                CounterLocation::AfterFixed => fixed.len(),
+                _ => 0,
+            };
+            let kdf: _ = kdf.counter_location(CounterLocation::MiddleFixed(offset));
+            assert_eq!(unhex(exp), derive(&kdf, &fixed, 16));
+        }
+    }
+
+    // the middle location, feedback and double-pipeline vectors below are not from the CAVP
+    // files. Feedback vectors with the counter after the iteration value were produced by
+    // OpenSSL 3.5's KBKDF, the others by the SP 800-108 definitions over OpenSSL's AES-CMAC.
+    // Each derives 40 bytes from KEY and fixed().
+    #[test]
+    fn test_counter_locations() {
+        let after: _ = Kbkdf::new(KEY, Mode::Counter).counter_location(CounterLocation::AfterFixed);
+        let exp: _ = unhex("ba06ba01d335e053836b6e0849c4c429edb6c47e9808eec38514781ad42b877007c1a167415c291b");
+        assert_eq!(exp, derive(&after, &fixed(), 40));
+
+        let middle: _ = Kbkdf::new(KEY, Mode::Counter).counter_location(CounterLocation::MiddleFixed(10));
+        let exp: _ = unhex("5513066e31e576c485c979668ad78cf6163b44e871c499c543233a2a4fbbf90b29e296f0941dd13d");
+        assert_eq!(exp, derive(&middle, &fixed(), 40));
+    }
+
+    #[test]
+    fn test_label_and_context() {
+        let kdf: _ = Kbkdf::new(KEY, Mode::Counter);
+        let mut out: _ = [0; 32];
+        kdf.derive(b"label", b"context", &mut out).unwrap();
+        assert_eq!(unhex("4a63599f94bd3bf38b763386bb5397c690a3a40600f96a691059cc001c163c49"), out);
+    }
+
+    // (IV, CTRLOCATION, RLEN in bytes, KO)
+    const FEEDBACK: [(&str, CounterLocation, usize, &str); 10] = [
+        ("00000000000000000000000000000000", CounterLocation::BeforeFixed, 1,
+         "a4e9bb0ad770653d2eb32abf74c0e96a212964297ddf64ca182a8e8c90a5e92dbcb4f2c3fafbd6b4"),
+        ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", CounterLocation::BeforeFixed, 1,
+         "864a80b51eb46e6abdb8cae37bf3655e191087f3e456d531555e27fd8d81d3294f95e2c2533a385e"),
+        ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", CounterLocation::BeforeFixed, 2,
+         "e0fc8ee086214815e5e3052c0fe83e003c3583a41a90754d9df8cc1f11604640715aa28503cf7510"),
+        ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", CounterLocation::BeforeFixed, 3,
+         "580aa1b6125c607e49ba4dfc032b80145cc1d03a342c0dcec4177a12d6fd2c88acb464b69f61cdb4"),
+        ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", CounterLocation::BeforeFixed, 4,
+         "55ca4329a21f253a1849449918d7ca3ac277c2acefc52c123fe7b1f92998aa25068254b77f676d8c"),
+        ("", CounterLocation::BeforeFixed, 1,
+         "a54fb6c708680f5071b8f3b222e8d916d62791fb007feea8be6c16a1773490722d6ca88f9e5de61b"),
+        ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", CounterLocation::BeforeIter, 4,
+         "e4bb190e747944c385a63e4d6d7a0b427c3f9cec570367fba0925aed5e9115579e2be862e36cf3e0"),
+        ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", CounterLocation::MiddleFixed(10), 1,
+         "0cca9605ad0e8e163a369692138c3eefcbcdbbb2e3827aee409a4954db6d90031808324ee4b7eb95"),
+        ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", CounterLocation::AfterFixed, 2,
+         "280b01bd42dbb0a2662bae0ec2b31ea890fd469a331290e680d44ef9939450d756f5b4c9b17e85ab"),
+        ("", CounterLocation::BeforeFixed, 0,
+         "72d00fd9a90681666d5429cb02a0055028989349be3bcb3982c2f47f3271c2c6ce3f0936ac42b93d"),
+    ];
+
+    #[test]
+    fn test_feedback() {
+        for (iv, location, width, exp) in FEEDBACK.iter() {
+            let kdf: _ = Kbkdf::new(KEY, Mode::Feedback(unhex(iv)))
+                .counter_location(*location)
+                .counter_width(*width);
+            assert_eq!(unhex(exp), derive(&kdf, &fixed(), 40));
+        }
+    }
+
+    // (CTRLOCATION, RLEN in bytes, KO)
+    const DOUBLE_PIPELINE: [(CounterLocation, usize, &str); 7] = [
+        (CounterLocation::BeforeFixed, 1,
+         "bc0d7f7b52a90a1ea8a7e28eb592b937ad652a6646700fa9cacee21529640aee517ef50090f934b6"),
+        (CounterLocation::BeforeFixed, 4,
+         "23819e17cb312819248f2add836af3cca0dbbb07c23405b065422da9d61558affb2aabf1493a95f5"),
+        (CounterLocation::BeforeIter, 4,
+         "3f8252e18f9a1136f1d4e63e5567f228b4ac6a4530820df4ea66ec6e85662b79798a85c8ff7bece0"),
+        (CounterLocation::MiddleFixed(0), 4,
+         "23819e17cb312819248f2add836af3cca0dbbb07c23405b065422da9d61558affb2aabf1493a95f5"),
+        (CounterLocation::MiddleFixed(10), 2,
+         "45605673c881426501c4ac9ae8c13861187fd838995c66fb2b7ea0943c6ae7349a978a563c711409"),
+        (CounterLocation::AfterFixed, 4,
+         "9434d4a1f9b6834d93c9a3c401f5a94a1ba5a0599cc52ad7b35faa87717f26688eb5c6d30729c6e6"),
+        (CounterLocation::BeforeFixed, 0,
+         "28989349be3bcb3982c2f47f3271c2c66eb043e6387719d948908c7b06cece070960884959faf383"),
+    ];
+
+    #[test]
+    fn test_double_pipeline() {
+        for (location, width, exp) in DOUBLE_PIPELINE.iter() {
+            let kdf: _ = Kbkdf::new(KEY, Mode::DoublePipeline)
+                .counter_location(*location)
+                .counter_width(*width);
+            assert_eq!(unhex(exp), derive(&kdf, &fixed(), 40));
+        }
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        let mut out: _ = [0; 16];
+        let kdf: _ = Kbkdf::new(KEY, Mode::Counter).counter_width(0);
+        assert!(kdf.derive_fixed(&fixed(), &mut out).is_err());
+
+        let kdf: _ = Kbkdf::new(KEY, Mode::Counter).counter_location(CounterLocation::MiddleFixed(33));
+        assert!(kdf.derive_fixed(&fixed(), &mut out).is_err());
+
+        let kdf: _ = Kbkdf::new(KEY, Mode::Counter).length_width(5);
+        assert!(kdf.derive(b"", b"", &mut out).is_err());
+
+        // an 8-bit counter covers 255 blocks and an 8-bit L covers 31 bytes.
+        let mut long: _ = vec![0; 255 * BLOCK_SIZE + 1];
+        let kdf: _ = Kbkdf::new(KEY, Mode::Counter).counter_width(1);
+        assert_eq!(Err(Error::OutputTooLong { len: long.len(), max: 255 * BLOCK_SIZE }), kdf.derive_fixed(&fixed(), &mut long));
+
+        let kdf: _ = Kbkdf::new(KEY, Mode::Counter).length_width(1);
+        assert_eq!(Err(Error::OutputTooLong { len: 32, max: 31 }), kdf.derive(b"", b"", &mut [0; 32]));
+    }
+}
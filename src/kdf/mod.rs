@@ -0,0 +1,3 @@
+mod kbkdf;
+
+pub use kbkdf::{CounterLocation, Kbkdf, Mode};
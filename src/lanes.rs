@@ -0,0 +1,232 @@
+//! N independent messages, each under its own key, run in lockstep on one thread: block i
+//! of every lane goes through the rounds before block i + 1 of any. A backend that
+//! overrides Backend::encrypt_lanes overlaps the lanes' rounds, the others run them in
+//! turn, and either way each lane comes out as if it had been processed on its own.
+//! N is meant to be 4 or 8.
+
+use crate::{backend::Backend, modes::cbc::check_aligned, to_block, xor_block, Block, BLOCK_SIZE, Error};
+
+pub type Lanes4<B> = Lanes<B, 4>;
+pub type Lanes8<B> = Lanes<B, 8>;
+
+#[derive(Debug, Clone)]
+pub struct Lanes<B, const N: usize> {
+    ciphers: [B; N],
+}
+
+impl<B: Backend, const N: usize> Lanes<B, N> {
+    pub fn new(keys: [Block; N]) -> Self {
+        Self { ciphers: keys.map(B::expand_key) }
+    }
+
+    pub fn from_ciphers(ciphers: [B; N]) -> Self {
+        Self { ciphers }
+    }
+
+    pub fn lane(&self, idx: usize) -> &B {
+        &self.ciphers[idx]
+    }
+
+    pub fn encrypt_blocks(&self, blocks: &mut [Block; N]) {
+        B::encrypt_lanes(&self.ciphers, blocks)
+    }
+
+    pub fn decrypt_blocks(&self, blocks: &mut [Block; N]) {
+        B::decrypt_lanes(&self.ciphers, blocks)
+    }
+
+    pub fn ecb_encrypt(&self, bufs: [&mut [u8]; N]) -> Result<(), Error> {
+        self.aligned(&bufs)?;
+        self.lockstep(bufs, &mut [[0; BLOCK_SIZE]; N], B::encrypt_lanes, |_, chunk| to_block(chunk), |_, chunk, block| {
+            chunk.copy_from_slice(block)
+        });
+        Ok(())
+    }
+
+    pub fn ecb_decrypt(&self, bufs: [&mut [u8]; N]) -> Result<(), Error> {
+        self.aligned(&bufs)?;
+        self.lockstep(bufs, &mut [[0; BLOCK_SIZE]; N], B::decrypt_lanes, |_, chunk| to_block(chunk), |_, chunk, block| {
+            chunk.copy_from_slice(block)
+        });
+        Ok(())
+    }
+
+    // CBC as in modes::Cbc, chaining within each lane from its own iv.
+    pub fn cbc_encrypt(&self, mut ivs: [Block; N], bufs: [&mut [u8]; N]) -> Result<(), Error> {
+        self.aligned(&bufs)?;
+        let load: _ = |prev: &mut Block, chunk: &[u8]| {
+            let mut block: Block = *prev;
+            xor_block(&mut block, &to_block(chunk));
+            block
+        };
+        let store: _ = |prev: &mut Block, chunk: &mut [u8], block: &Block| {
+            chunk.copy_from_slice(block);
+            *prev = *block;
+        };
+        self.lockstep(bufs, &mut ivs, B::encrypt_lanes, load, store);
+        Ok(())
+    }
+
+    // the ciphertext block is still in the buffer when its plaintext is stored over it.
+    pub fn cbc_decrypt(&self, mut ivs: [Block; N], bufs: [&mut [u8]; N]) -> Result<(), Error> {
+        self.aligned(&bufs)?;
+        let store: _ = |prev: &mut Block, chunk: &mut [u8], block: &Block| {
+            let cipher_block: Block = to_block(chunk);
+            let mut plain_block: Block = *block;
+            xor_block(&mut plain_block, prev);
+            chunk.copy_from_slice(&plain_block);
+            *prev = cipher_block;
+        };
+        self.lockstep(bufs, &mut ivs, B::decrypt_lanes, |_, chunk| to_block(chunk), store);
+        Ok(())
+    }
+
+    // counter mode as in modes::Ctr, with each lane's iv as its big-endian starting counter.
+    pub fn ctr_apply_keystream(&self, mut ivs: [Block; N], bufs: [&mut [u8]; N]) {
+        let load: _ = |counter: &mut Block, _: &[u8]| {
+            let block: Block = *counter;
+            *counter = u128::from_be_bytes(*counter).wrapping_add(1).to_be_bytes();
+            block
+        };
+        let store: _ = |_: &mut Block, chunk: &mut [u8], keystream: &Block| {
+            chunk.iter_mut().zip(keystream.iter()).for_each(|(val, key)| *val ^= key)
+        };
+        self.lockstep(bufs, &mut ivs, B::encrypt_lanes, load, store)
+    }
+
+    fn aligned(&self, bufs: &[&mut [u8]; N]) -> Result<(), Error> {
+        bufs.iter().try_for_each(|buf| check_aligned(buf))
+    }
+
+    // step i loads block i of every lane that has one, runs the lanes through op together
+    // and stores the results back. Lanes with fewer blocks carry a zero block once they run
+    // out, and its result is dropped. chain holds whatever a lane carries between its blocks.
+    fn lockstep<L, S>(&self, mut bufs: [&mut [u8]; N], chain: &mut [Block; N], op: fn(&[B; N], &mut [Block; N]), mut load: L, mut store: S)
+    where
+        L: FnMut(&mut Block, &[u8]) -> Block,
+        S: FnMut(&mut Block, &mut [u8], &Block)
+    {
+        let steps: _ = bufs.iter().map(|buf| buf.len().div_ceil(BLOCK_SIZE)).max().unwrap_or(0);
+        for step in 0..steps {
+            let mut blocks: [Block; N] = [[0; BLOCK_SIZE]; N];
+            for ((block, buf), prev) in blocks.iter_mut().zip(bufs.iter()).zip(chain.iter_mut()) {
+                if let Some(chunk) = buf.chunks(BLOCK_SIZE).nth(step) {
+                    *block = load(prev, chunk)
+                }
+            }
+            op(&self.ciphers, &mut blocks);
+            for ((block, buf), prev) in blocks.iter().zip(bufs.iter_mut()).zip(chain.iter_mut()) {
+                if let Some(chunk) = buf.chunks_mut(BLOCK_SIZE).nth(step) {
+                    store(prev, chunk, block)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{backend, modes, RjindaelCipher};
+
+    fn keys<const N: usize>() -> [Block; N] {
+        std::array::from_fn(|idx| [idx as u8 * 17 + 1; BLOCK_SIZE])
+    }
+
+    fn ivs<const N: usize>() -> [Block; N] {
+        std::array::from_fn(|idx| {
+            let mut iv: Block = [0xff; BLOCK_SIZE];
+            iv[0] = idx as u8;
+            iv
+        })
+    }
+
+    // a different length in every lane, the empty one included.
+    fn messages<const N: usize>(unit: usize) -> [Vec<u8>; N] {
+        std::array::from_fn(|idx| (0..unit * (idx * 3 % 7)).map(|x| (x * 7 + idx) as u8).collect())
+    }
+
+    fn borrow<const N: usize>(bufs: &mut [Vec<u8>; N]) -> [&mut [u8]; N] {
+        let mut iter: _ = bufs.iter_mut();
+        std::array::from_fn(|_| iter.next().unwrap().as_mut_slice())
+    }
+
+    fn check<B: Backend, const N: usize>() {
+        let lanes: _ = Lanes::<B, N>::new(keys());
+        let ciphers: [RjindaelCipher; N] = keys().map(RjindaelCipher::new);
+
+        let mut test: [Block; N] = std::array::from_fn(|idx| [idx as u8; BLOCK_SIZE]);
+        lanes.encrypt_blocks(&mut test);
+        for (idx, (block, cipher)) in test.iter().zip(ciphers.iter()).enumerate() {
+            let mut exp: Block = [idx as u8; BLOCK_SIZE];
+            cipher.encrypt_block(&mut exp);
+            assert_eq!(exp, *block);
+        }
+        lanes.decrypt_blocks(&mut test);
+        assert_eq!(std::array::from_fn::<Block, N, _>(|idx| [idx as u8; BLOCK_SIZE]), test);
+
+        let mut test: _ = messages::<N>(BLOCK_SIZE);
+        lanes.ecb_encrypt(borrow(&mut test)).unwrap();
+        for ((test, mut exp), cipher) in test.iter().zip(messages::<N>(BLOCK_SIZE)).zip(ciphers.iter()) {
+            modes::Ecb::new(cipher.clone()).encrypt(&mut exp).unwrap();
+            assert_eq!(exp, *test);
+        }
+        lanes.ecb_decrypt(borrow(&mut test)).unwrap();
+        assert_eq!(messages::<N>(BLOCK_SIZE), test);
+
+        let mut test: _ = messages::<N>(BLOCK_SIZE);
+        lanes.cbc_encrypt(ivs(), borrow(&mut test)).unwrap();
+        for (((test, mut exp), cipher), iv) in test.iter().zip(messages::<N>(BLOCK_SIZE)).zip(ciphers.iter()).zip(ivs::<N>()) {
+            modes::Cbc::new(cipher.clone(), iv).encrypt(&mut exp).unwrap();
+            assert_eq!(exp, *test);
+        }
+        lanes.cbc_decrypt(ivs(), borrow(&mut test)).unwrap();
+        assert_eq!(messages::<N>(BLOCK_SIZE), test);
+
+        // lengths that end mid-block, and counters that carry into the first byte.
+        let mut test: _ = messages::<N>(13);
+        lanes.ctr_apply_keystream(ivs(), borrow(&mut test));
+        for (((test, mut exp), cipher), iv) in test.iter().zip(messages::<N>(13)).zip(ciphers.iter()).zip(ivs::<N>()) {
+            modes::Ctr::new(cipher.clone(), iv).apply_keystream(&mut exp);
+            assert_eq!(exp, *test);
+        }
+    }
+
+    #[test]
+    fn test_backends() {
+        check::<RjindaelCipher, 4>();
+        check::<RjindaelCipher, 8>();
+        check::<backend::Bitsliced, 4>();
+        check::<backend::AesNi, 4>();
+        check::<backend::AesNi, 8>();
+        #[cfg(feature = "ttable")]
+        check::<backend::TTable, 8>();
+    }
+
+    #[test]
+    fn test_software_fallback() {
+        // one lane without the hardware path takes the whole batch off it.
+        let keys: [Block; 4] = keys();
+        let mut ciphers: [backend::AesNi; 4] = keys.map(backend::AesNi::new);
+        ciphers[2] = backend::AesNi::software(keys[2]);
+        let lanes: _ = Lanes4::from_ciphers(ciphers);
+        let mut test: [Block; 4] = [[0x5a; BLOCK_SIZE]; 4];
+        lanes.encrypt_blocks(&mut test);
+        for (block, key) in test.iter().zip(keys.iter()) {
+            let mut exp: Block = [0x5a; BLOCK_SIZE];
+            RjindaelCipher::new(*key).encrypt_block(&mut exp);
+            assert_eq!(exp, *block);
+        }
+        assert!(!lanes.lane(2).is_hardware());
+    }
+
+    #[test]
+    fn test_unaligned() {
+        let lanes: _ = Lanes4::<RjindaelCipher>::new(keys());
+        let mut bufs: [Vec<u8>; 4] = [vec![0; 16], vec![0; 32], vec![0; 17], vec![]];
+        assert_eq!(Err(Error::UnalignedInput(17)), lanes.ecb_encrypt(borrow(&mut bufs)));
+        assert_eq!(Err(Error::UnalignedInput(17)), lanes.cbc_decrypt(ivs(), borrow(&mut bufs)));
+        // nothing is touched before the lengths are checked.
+        assert_eq!(vec![0; 32], bufs[1]);
+    }
+}
@@ -1,46 +1,259 @@
+// without the std feature the crate needs neither std nor alloc. Tests always have std.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![warn(clippy::all)]
+#![allow(clippy::let_with_type_underscore)]
 
 mod boxes;
+mod error;
 mod primitives;
+pub mod backend;
+#[cfg(feature = "std")]
+pub mod fpe;
+#[cfg(feature = "std")]
+pub mod hash;
+#[cfg(feature = "std")]
+pub mod kdf;
+pub mod lanes;
+pub mod mac;
+#[cfg(feature = "std")]
+pub mod mds;
+pub mod modes;
+#[cfg(feature = "std")]
+pub mod parallel;
+#[cfg(feature = "std")]
+pub mod rng;
+pub mod secret;
+#[cfg(feature = "std")]
+pub mod spn;
+#[cfg(feature = "std")]
+pub mod stream;
 
-use primitives::{state};
+#[cfg(test)]
+mod test_util;
 
-#[derive(Debug)]
+use primitives::{sbox, state};
+
+pub use error::Error;
+pub use primitives::field::Field;
+pub use primitives::sbox::{Affine, ReverseSubBox, SubBox};
+
+pub const BLOCK_SIZE: usize = 16;
+
+pub type Block = [u8; BLOCK_SIZE];
+
+// the key is expanded and the substitution boxes are built once, when the cipher is made,
+// so that every block after that reuses them. A block runs through the rounds in place,
+// with no heap use.
+#[derive(Debug, Clone)]
 pub struct RjindaelCipher {
-    text: state::State,
-    key: state::State,
+    keys: [Block; RjindaelCipher::ROUNDS + 1],
+    reverse_keys: [Block; RjindaelCipher::ROUNDS + 1],
+    sbox: sbox::SubBox,
+    rsbox: sbox::ReverseSubBox,
 }
 
 impl RjindaelCipher {
-    fn new(text: state::State, key: state::State) -> Self {
-        Self { text, key }
-    }
+    const ROUNDS: usize = 10;
+
+    pub fn new(key: Block) -> Self {
+        let mut keys: [Block; Self::ROUNDS + 1] = [key; Self::ROUNDS + 1];
+        let mut ksf: _ = boxes::KeySchedule::new(state::State::from(key));
+        for skey in keys[1..].iter_mut() {
+            *skey = (*ksf.next()).into()
+        }
+
+        let mut reverse_keys: [Block; Self::ROUNDS + 1] = keys;
+        let mut rksf: _ = boxes::ReverseKeySchedule::new(state::State::from(key));
+        for skey in reverse_keys.iter_mut() {
+            *skey = rksf.next().into()
+        }
 
-    fn encrypt(self) -> state::State {
-        let mut ksf: _ = boxes::KeySchedule::new(self.key);
-        let mut rnd: _ = boxes::Round::new(self.text);
+        Self {
+            keys,
+            reverse_keys,
+            sbox: sbox::SubBox::default(),
+            rsbox: sbox::ReverseSubBox::default()
+        }
+    }
 
-        for _ in 0..9 {
-            let skey: _ = ksf.next();
+    pub fn encrypt_block(&self, block: &mut Block) {
+        let mut rnd: _ = boxes::Round::new(*block, &self.sbox);
+        rnd.first(&self.keys[0]);
+        for skey in self.keys[1..Self::ROUNDS].iter() {
             rnd.next(skey);
         }
-        let last_key: _ = ksf.next();
-        let cipher: _ = rnd.last(last_key);
+        *block = rnd.last(&self.keys[Self::ROUNDS])
+    }
+
+    pub fn decrypt_block(&self, block: &mut Block) {
+        let mut rrnd: _ = boxes::ReverseRound::new(*block, &self.rsbox);
+        rrnd.first(&self.reverse_keys[0]);
+        for skey in self.reverse_keys[1..Self::ROUNDS].iter() {
+            rrnd.next(skey);
+        }
+        *block = rrnd.last(&self.reverse_keys[Self::ROUNDS])
+    }
 
-        cipher
+    // overwrites the round keys, for a cipher whose key must not outlive it.
+    pub(crate) fn zeroize(&mut self) {
+        for skey in self.keys.iter_mut().chain(self.reverse_keys.iter_mut()) {
+            zeroize(skey)
+        }
     }
 
-    fn decrypt(self) -> state::State {
-        let mut rksf: _ = boxes::ReverseKeySchedule::new(self.key);
-        let mut rrnd: _ = boxes::ReverseRound::new(self.text);
+    // one block under each cipher, every lane taking a round before any takes the next, so
+    // that the table lookups of the lanes overlap.
+    pub(crate) fn encrypt_lanes<const N: usize>(lanes: &[Self; N], blocks: &mut [Block; N]) {
+        let mut rnds: [boxes::Round; N] = core::array::from_fn(|idx| boxes::Round::new(blocks[idx], &lanes[idx].sbox));
+        for (rnd, lane) in rnds.iter_mut().zip(lanes.iter()) {
+            rnd.first(&lane.keys[0]);
+        }
+        for round in 1..Self::ROUNDS {
+            for (rnd, lane) in rnds.iter_mut().zip(lanes.iter()) {
+                rnd.next(&lane.keys[round]);
+            }
+        }
+        for ((block, rnd), lane) in blocks.iter_mut().zip(IntoIterator::into_iter(rnds)).zip(lanes.iter()) {
+            *block = rnd.last(&lane.keys[Self::ROUNDS])
+        }
+    }
 
-        for _ in 0..9 {
-            let skey: _ = rksf.next();
-            rrnd.next(&skey);
+    pub(crate) fn decrypt_lanes<const N: usize>(lanes: &[Self; N], blocks: &mut [Block; N]) {
+        let mut rrnds: [boxes::ReverseRound; N] = core::array::from_fn(|idx| boxes::ReverseRound::new(blocks[idx], &lanes[idx].rsbox));
+        for (rrnd, lane) in rrnds.iter_mut().zip(lanes.iter()) {
+            rrnd.first(&lane.reverse_keys[0]);
+        }
+        for round in 1..Self::ROUNDS {
+            for (rrnd, lane) in rrnds.iter_mut().zip(lanes.iter()) {
+                rrnd.next(&lane.reverse_keys[round]);
+            }
         }
-        let initial_key: _ = rksf.next();
-        let plain: _ = rrnd.last(&initial_key);
+        for ((block, rrnd), lane) in blocks.iter_mut().zip(IntoIterator::into_iter(rrnds)).zip(lanes.iter()) {
+            *block = rrnd.last(&lane.reverse_keys[Self::ROUNDS])
+        }
+    }
+}
 
-        plain
+// the FIPS-197 round keys of key, computed by the compiler when called in const context.
+pub const fn expand_key_const(key: Block) -> [Block; RjindaelCipher::ROUNDS + 1] {
+    boxes::KeySchedule::expand(key)
+}
+
+// one block encrypted under key in const context, for constants such as
+// `const CT: Block = aes::encrypt_const(KEY, PT);`. Each call expands the key again, so at
+// run time RjindaelCipher is the one to use.
+pub const fn encrypt_const(key: Block, block: Block) -> Block {
+    boxes::encrypt_const(&expand_key_const(key), block)
+}
+
+pub(crate) fn to_block(chunk: &[u8]) -> Block {
+    let mut buf: Block = [0; BLOCK_SIZE];
+    buf.copy_from_slice(chunk);
+    buf
+}
+
+// volatile writes, which the compiler may not drop as dead stores before the memory is freed.
+pub(crate) fn zeroize(buf: &mut [u8]) {
+    for val in buf.iter_mut() {
+        // val comes from a &mut u8, so it is valid and aligned for the write.
+        unsafe { core::ptr::write_volatile(val, 0) }
+    }
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+}
+
+pub(crate) fn xor_block(lhs: &mut Block, rhs: &Block) {
+    for (l, r) in lhs.iter_mut().zip(rhs.iter()) {
+        *l ^= r
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_block() {
+        // FIPS-197 Appendix B
+        let cipher: _ = RjindaelCipher::new([
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6,
+            0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c
+        ]);
+        let mut test: Block = [
+            0x32, 0x43, 0xf6, 0xa8, 0x88, 0x5a, 0x30, 0x8d,
+            0x31, 0x31, 0x98, 0xa2, 0xe0, 0x37, 0x07, 0x34
+        ];
+        cipher.encrypt_block(&mut test);
+        let exp: Block = [
+            0x39, 0x25, 0x84, 0x1d, 0x02, 0xdc, 0x09, 0xfb,
+            0xdc, 0x11, 0x85, 0x97, 0x19, 0x6a, 0x0b, 0x32
+        ];
+        assert_eq!(exp, test)
+    }
+
+    #[test]
+    fn test_decrypt_block() {
+        // FIPS-197 Appendix C.1
+        let cipher: _ = RjindaelCipher::new([
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+            0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f
+        ]);
+        let mut test: Block = [
+            0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30,
+            0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5, 0x5a
+        ];
+        cipher.decrypt_block(&mut test);
+        let exp: Block = [
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77,
+            0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff
+        ];
+        assert_eq!(exp, test)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let cipher: _ = RjindaelCipher::new([0x5a; 16]);
+        let mut test: Block = *b"sixteen byte msg";
+        cipher.encrypt_block(&mut test);
+        assert_ne!(*b"sixteen byte msg", test);
+        cipher.decrypt_block(&mut test);
+        assert_eq!(*b"sixteen byte msg", test)
+    }
+
+    #[test]
+    fn test_zeroize() {
+        let mut cipher: _ = RjindaelCipher::new([0x5a; 16]);
+        cipher.zeroize();
+        assert_eq!([[0; BLOCK_SIZE]; RjindaelCipher::ROUNDS + 1], cipher.keys);
+        assert_eq!([[0; BLOCK_SIZE]; RjindaelCipher::ROUNDS + 1], cipher.reverse_keys);
+    }
+
+    #[test]
+    fn test_encrypt_const() {
+        // FIPS-197 Appendix B, computed by the compiler.
+        const KEY: Block = [
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6,
+            0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c
+        ];
+        const CT: Block = encrypt_const(KEY, [
+            0x32, 0x43, 0xf6, 0xa8, 0x88, 0x5a, 0x30, 0x8d,
+            0x31, 0x31, 0x98, 0xa2, 0xe0, 0x37, 0x07, 0x34
+        ]);
+        const ROUND_KEYS: [Block; 11] = expand_key_const(KEY);
+        let exp: Block = [
+            0x39, 0x25, 0x84, 0x1d, 0x02, 0xdc, 0x09, 0xfb,
+            0xdc, 0x11, 0x85, 0x97, 0x19, 0x6a, 0x0b, 0x32
+        ];
+        assert_eq!(exp, CT);
+        assert_eq!([0xd0, 0x14, 0xf9, 0xa8], ROUND_KEYS[10][..4]);
+
+        // the const path agrees with the run time one.
+        for seed in 0..64u8 {
+            let key: Block = [seed.wrapping_mul(0x3b) ^ 0x5c; 16];
+            let mut test: Block = [seed; 16];
+            test[seed as usize % 16] ^= 0xa5;
+            let exp: _ = encrypt_const(key, test);
+            RjindaelCipher::new(key).encrypt_block(&mut test);
+            assert_eq!(exp, test);
+        }
     }
 }
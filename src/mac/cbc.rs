@@ -0,0 +1,186 @@
+//! Raw CBC-MAC (ISO/IEC 9797-1 MAC algorithm 1) with a zero IV.
+//! CBC-MAC is only secure for messages of a fixed length unless the length is
+//! bound into the first block, either through padding method 3 or by prepending it.
+
+use crate::{mac, xor_block, Block, Error, BLOCK_SIZE, RjindaelCipher};
+
+#[derive(Debug, Clone)]
+pub struct CbcMac {
+    cipher: RjindaelCipher,
+    padding: mac::Padding,
+    prepend_length: bool,
+    tag_len: usize,
+}
+
+impl CbcMac {
+    pub fn new(cipher: RjindaelCipher, padding: mac::Padding) -> Self {
+        Self {
+            cipher,
+            padding,
+            prepend_length: false,
+            tag_len: BLOCK_SIZE
+        }
+    }
+
+    // prefixes the message with a block holding its bit length before padding methods 1 and 2.
+    // padding method 3 already carries the length, so this has no effect there.
+    pub fn prepend_length(mut self) -> Self {
+        self.prepend_length = true;
+        self
+    }
+
+    // the message is chained in place, so no padded copy of it is made. The length block of
+    // method 3 is the same block prepend_length adds.
+    pub fn mac(&self, msg: &[u8]) -> Block {
+        let mut acc: Block = [0; BLOCK_SIZE];
+        if self.prepend_length || self.padding == mac::Padding::Length {
+            acc = mac::padding::length_block(msg.len());
+            self.cipher.encrypt_block(&mut acc);
+        }
+        acc = chain(&self.cipher, acc, &msg[..msg.len() - msg.len() % BLOCK_SIZE]);
+        match self.padding.last_block(msg) {
+            Some(last) => chain(&self.cipher, acc, &last),
+            None => acc
+        }
+    }
+
+    // tags are a full block unless set shorter. verify rejects a tag of any other length.
+    pub fn tag_len(mut self, len: usize) -> Result<Self, Error> {
+        self.tag_len = mac::check_tag_len(len)?;
+        Ok(self)
+    }
+
+    pub fn verify(&self, msg: &[u8], tag: &[u8]) -> bool {
+        mac::verify_truncated(&self.mac(msg), tag, self.tag_len)
+    }
+}
+
+// runs the CBC chain over whole blocks starting from the accumulator, returning the last block.
+pub(crate) fn chain(cipher: &RjindaelCipher, mut acc: Block, data: &[u8]) -> Block {
+    debug_assert_eq!(0, data.len() % BLOCK_SIZE);
+    for chunk in data.chunks(BLOCK_SIZE) {
+        for (val, byte) in acc.iter_mut().zip(chunk) {
+            *val ^= byte
+        }
+        cipher.encrypt_block(&mut acc);
+    }
+    acc
+}
+
+// chains a message whose final block is masked before the last encryption, as XCBC and CMAC do.
+// a complete final block is masked with one key, a partial one is padded with 10* and masked with the other.
+pub(crate) fn chain_masked(cipher: &RjindaelCipher, msg: &[u8], complete: &Block, partial: &Block) -> Block {
+    // the last block is always processed separately, even when it is complete.
+    let split: _ = match msg.len() % BLOCK_SIZE {
+        0 if !msg.is_empty() => msg.len() - BLOCK_SIZE,
+        rem => msg.len() - rem
+    };
+    let (head, tail): _ = msg.split_at(split);
+
+    let mut last: Block = [0; BLOCK_SIZE];
+    last[..tail.len()].copy_from_slice(tail);
+    if tail.len() == BLOCK_SIZE {
+        xor_block(&mut last, complete);
+    } else {
+        last[tail.len()] = 0x80;
+        xor_block(&mut last, partial);
+    }
+
+    let mut acc: _ = chain(cipher, [0; BLOCK_SIZE], head);
+    xor_block(&mut acc, &last);
+    cipher.encrypt_block(&mut acc);
+    acc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_cipher() -> RjindaelCipher {
+        RjindaelCipher::new([
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6,
+            0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c
+        ])
+    }
+
+    // SP 800-38A F.2.1 plaintext; expected tags were cross-checked against the last
+    // block of CBC-AES128 with a zero IV from an independent implementation.
+    const MSG: [u8; 64] = [
+        0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a,
+        0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac, 0x45, 0xaf, 0x8e, 0x51,
+        0x30, 0xc8, 0x1c, 0x46, 0xa3, 0x5c, 0xe4, 0x11, 0xe5, 0xfb, 0xc1, 0x19, 0x1a, 0x0a, 0x52, 0xef,
+        0xf6, 0x9f, 0x24, 0x45, 0xdf, 0x4f, 0x9b, 0x17, 0xad, 0x2b, 0x41, 0x7b, 0xe6, 0x6c, 0x37, 0x10
+    ];
+
+    const BIT_TAG: Block = [
+        0x60, 0x49, 0x9a, 0x87, 0x1a, 0x40, 0x60, 0x77,
+        0xfa, 0xfa, 0x66, 0x62, 0xcf, 0xa2, 0xe2, 0x8d
+    ];
+
+    const LENGTH_TAG: Block = [
+        0x58, 0xc3, 0xaf, 0x4c, 0x63, 0xb1, 0x7d, 0xac,
+        0x75, 0x02, 0xee, 0x7d, 0xdd, 0xca, 0xdd, 0x3c
+    ];
+
+    const PREPEND_BIT_TAG: Block = [
+        0x0a, 0x72, 0x5e, 0xe6, 0xb7, 0x04, 0xad, 0x47,
+        0x7b, 0xe9, 0x59, 0x7c, 0x55, 0x2c, 0xf5, 0x09
+    ];
+
+    #[test]
+    fn test_zeros() {
+        let mac: _ = CbcMac::new(test_cipher(), mac::Padding::Zeros);
+        let exp: Block = [
+            0xa7, 0x35, 0x6e, 0x12, 0x07, 0xbb, 0x40, 0x66,
+            0x39, 0xe5, 0xe5, 0xce, 0xb9, 0xa9, 0xed, 0x93
+        ];
+        assert_eq!(exp, mac.mac(&MSG))
+    }
+
+    #[test]
+    fn test_bit() {
+        let mac: _ = CbcMac::new(test_cipher(), mac::Padding::Bit);
+        let exp: Block = BIT_TAG;
+        assert_eq!(exp, mac.mac(&MSG[..20]))
+    }
+
+    #[test]
+    fn test_length() {
+        let mac: _ = CbcMac::new(test_cipher(), mac::Padding::Length);
+        let exp: Block = LENGTH_TAG;
+        assert_eq!(exp, mac.mac(&MSG[..20]))
+    }
+
+    #[test]
+    fn test_prepend_length() {
+        let zeros: _ = CbcMac::new(test_cipher(), mac::Padding::Zeros).prepend_length();
+        let length: _ = CbcMac::new(test_cipher(), mac::Padding::Length).prepend_length();
+        assert_eq!(length.mac(&MSG[..20]), zeros.mac(&MSG[..20]));
+
+        let bit: _ = CbcMac::new(test_cipher(), mac::Padding::Bit).prepend_length();
+        let exp: Block = PREPEND_BIT_TAG;
+        assert_eq!(exp, bit.mac(&MSG[..20]))
+    }
+
+    #[test]
+    fn test_verify() {
+        let mac: _ = CbcMac::new(test_cipher(), mac::Padding::Bit);
+        assert!(mac.verify(&MSG[..20], &BIT_TAG));
+        assert!(!mac.verify(&MSG[..20], &BIT_TAG[..8]));
+        assert!(!mac.verify(&MSG[..20], &[]));
+
+        let mac: _ = mac.tag_len(8).unwrap();
+        assert!(mac.verify(&MSG[..20], &BIT_TAG[..8]));
+        assert!(!mac.verify(&MSG[..21], &BIT_TAG[..8]));
+        assert!(!mac.verify(&MSG[..20], &BIT_TAG[..9]));
+        assert!(!mac.verify(&MSG[..20], &BIT_TAG[..1]));
+        assert!(!mac.verify(&MSG[..20], &BIT_TAG));
+    }
+
+    #[test]
+    fn test_invalid_tag_len() {
+        for len in [0, 1, 7, 17].iter() {
+            assert!(CbcMac::new(test_cipher(), mac::Padding::Bit).tag_len(*len).is_err())
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! AES-CMAC from SP 800-38B and RFC 4493.
+
+use crate::{mac, Block, Error, BLOCK_SIZE, RjindaelCipher};
+
+#[derive(Debug, Clone)]
+pub struct Cmac {
+    cipher: RjindaelCipher,
+    k1: Block,
+    k2: Block,
+    tag_len: usize,
+}
+
+impl Cmac {
+    pub fn new(key: Block) -> Self {
+        let cipher: _ = RjindaelCipher::new(key);
+        let mut zero: Block = [0; BLOCK_SIZE];
+        cipher.encrypt_block(&mut zero);
+        let k1: _ = double(&zero);
+        let k2: _ = double(&k1);
+
+        Self { cipher, k1, k2, tag_len: BLOCK_SIZE }
+    }
+
+    pub fn mac(&self, msg: &[u8]) -> Block {
+        mac::cbc::chain_masked(&self.cipher, msg, &self.k1, &self.k2)
+    }
+
+    // tags are a full block unless set shorter. verify rejects a tag of any other length.
+    pub fn tag_len(mut self, len: usize) -> Result<Self, Error> {
+        self.tag_len = mac::check_tag_len(len)?;
+        Ok(self)
+    }
+
+    pub fn verify(&self, msg: &[u8], tag: &[u8]) -> bool {
+        mac::verify_truncated(&self.mac(msg), tag, self.tag_len)
+    }
+}
+
+// multiplication by x in GF(2^128) under x^128 + x^7 + x^2 + x + 1, big-endian.
+pub(crate) fn double(block: &Block) -> Block {
+    let mut buf: Block = [0; BLOCK_SIZE];
+    let mut carry: u8 = 0;
+    for (out, val) in buf.iter_mut().zip(block.iter()).rev() {
+        *out = (val << 1) | carry;
+        carry = val >> 7;
+    }
+    buf[BLOCK_SIZE - 1] ^= 0x87 * carry;
+    buf
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::unhex;
+
+    const KEY: Block = [
+        0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6,
+        0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c
+    ];
+
+    #[test]
+    fn test_subkeys() {
+        let cmac: _ = Cmac::new(KEY);
+        assert_eq!(unhex("fbeed618357133667c85e08f7236a8de"), cmac.k1);
+        assert_eq!(unhex("f7ddac306ae266ccf90bc11ee46d513b"), cmac.k2);
+    }
+
+    // RFC 4493 section 4
+    #[test]
+    fn test_rfc_4493_vectors() {
+        let msg: _ = unhex(concat!(
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51",
+            "30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710"
+        ));
+        let cases: [(usize, &str); 4] = [
+            (0, "bb1d6929e95937287fa37d129b756746"),
+            (16, "070a16b46b4d4144f79bdd9dd04a287c"),
+            (40, "dfa66747de9ae63030ca32611497c827"),
+            (64, "51f0bebf7e3b9d92fc49741779363cfe"),
+        ];
+        let cmac: _ = Cmac::new(KEY);
+        let short: _ = Cmac::new(KEY).tag_len(8).unwrap();
+
+        for (len, exp) in cases.iter() {
+            assert_eq!(unhex(exp), cmac.mac(&msg[..*len]));
+            assert!(cmac.verify(&msg[..*len], &unhex(exp)));
+            assert!(!cmac.verify(&msg[..*len], &unhex(exp)[..8]));
+            assert!(short.verify(&msg[..*len], &unhex(exp)[..8]));
+        }
+    }
+}
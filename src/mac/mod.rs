@@ -0,0 +1,31 @@
+pub(crate) mod cbc;
+mod cmac;
+mod padding;
+mod xcbc;
+
+pub use cbc::CbcMac;
+pub use cmac::Cmac;
+pub use padding::Padding;
+pub use xcbc::XcbcMac;
+
+use crate::{Error, BLOCK_SIZE};
+
+// the shortest tag verify accepts. SP 800-38B warns against tags under 64 bits.
+pub const MIN_TAG_LEN: usize = 8;
+
+pub(crate) fn check_tag_len(len: usize) -> Result<usize, Error> {
+    match (MIN_TAG_LEN..=BLOCK_SIZE).contains(&len) {
+        true => Ok(len),
+        false => Err(Error::InvalidParameter("the tag length must lie in 8..=16 bytes"))
+    }
+}
+
+// checks a tag truncated to exactly tag_len bytes without an early exit on the first mismatch.
+pub(crate) fn verify_truncated(mac: &[u8], tag: &[u8], tag_len: usize) -> bool {
+    if tag.len() != tag_len || tag_len > mac.len() {
+        return false
+    }
+    mac.iter()
+        .zip(tag)
+        .fold(0, |acc, (l, r)| acc | (l ^ r)) == 0
+}
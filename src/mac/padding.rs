@@ -0,0 +1,121 @@
+//! Padding methods of ISO/IEC 9797-1 for the block cipher MACs.
+
+use crate::{Block, BLOCK_SIZE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Padding {
+    // method 1: right-pad with zero bits, an empty message becomes a single zero block.
+    Zeros,
+    // method 2: append a single one bit and right-pad with zero bits.
+    Bit,
+    // method 3: prepend a block holding the bit length of the message and right-pad with zero bits.
+    Length,
+}
+
+impl Padding {
+    #[cfg(feature = "std")]
+    pub fn pad(self, msg: &[u8]) -> Vec<u8> {
+        let mut buf: Vec<u8> = match self {
+            Padding::Length => length_block(msg.len()).to_vec(),
+            Padding::Zeros | Padding::Bit => Vec::with_capacity(msg.len() + BLOCK_SIZE),
+        };
+        buf.extend_from_slice(msg);
+        if self == Padding::Bit {
+            buf.push(0x80)
+        }
+
+        let rem: _ = buf.len() % BLOCK_SIZE;
+        if rem != 0 || buf.is_empty() {
+            buf.resize(buf.len() + BLOCK_SIZE - rem, 0)
+        }
+        buf
+    }
+
+    // the block padding leaves after the whole blocks of msg, if there is one. The length
+    // block of method 3 goes in front and is left to the caller.
+    pub(crate) fn last_block(self, msg: &[u8]) -> Option<Block> {
+        let tail: _ = &msg[msg.len() - msg.len() % BLOCK_SIZE..];
+        let mut buf: Block = [0; BLOCK_SIZE];
+        buf[..tail.len()].copy_from_slice(tail);
+        match self {
+            Padding::Bit => {
+                buf[tail.len()] = 0x80;
+                Some(buf)
+            },
+            Padding::Zeros if msg.is_empty() => Some(buf),
+            _ if tail.is_empty() => None,
+            _ => Some(buf),
+        }
+    }
+}
+
+// the bit length of the message, big-endian and right-aligned in a block.
+pub(crate) fn length_block(len: usize) -> Block {
+    (len as u128 * 8).to_be_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_zeros() {
+        assert_eq!(vec![0; 16], Padding::Zeros.pad(&[]));
+        assert_eq!(vec![0xaa; 16], Padding::Zeros.pad(&[0xaa; 16]));
+
+        let mut exp: _ = vec![0xaa; 3];
+        exp.resize(16, 0);
+        assert_eq!(exp, Padding::Zeros.pad(&[0xaa; 3]));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_bit() {
+        let mut exp: _ = vec![0x80];
+        exp.resize(16, 0);
+        assert_eq!(exp, Padding::Bit.pad(&[]));
+
+        let mut exp: _ = vec![0xaa; 16];
+        exp.push(0x80);
+        exp.resize(32, 0);
+        assert_eq!(exp, Padding::Bit.pad(&[0xaa; 16]));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_length() {
+        assert_eq!(vec![0; 16], Padding::Length.pad(&[]));
+
+        let mut exp: _ = vec![0; 15];
+        exp.push(0x18);
+        exp.extend_from_slice(&[0xaa; 3]);
+        exp.resize(32, 0);
+        assert_eq!(exp, Padding::Length.pad(&[0xaa; 3]));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_last_block() {
+        // the whole blocks, then the last block, make up the padded message.
+        for padding in [Padding::Zeros, Padding::Bit, Padding::Length].iter() {
+            for len in [0, 3, 16, 31, 32].iter() {
+                let msg: _ = vec![0xaa; *len];
+                let mut exp: _ = padding.pad(&msg);
+                if *padding == Padding::Length {
+                    assert_eq!(length_block(*len).to_vec(), exp.drain(..BLOCK_SIZE).collect::<Vec<u8>>());
+                }
+                let mut test: _ = msg[..len - len % BLOCK_SIZE].to_vec();
+                test.extend(padding.last_block(&msg).iter().flatten());
+                assert_eq!(exp, test);
+            }
+        }
+    }
+
+    #[test]
+    fn test_length_block() {
+        let test: _ = length_block(1000);
+        assert_eq!([0x1f, 0x40], test[14..]);
+        assert!(test[..14].iter().all(|x| *x == 0));
+    }
+}
@@ -0,0 +1,311 @@
+//! Square matrices over GF(2^8), or a smaller field such as GF(16), for designing
+//! diffusion layers: the MDS check over every square submatrix, the differential branch
+//! number, inversion, the naive XOR count, and a search for the cheapest MDS circulant or
+//! Hadamard matrix over a set of entries. The arithmetic goes through the field tables,
+//! which is fine for public matrices.
+
+use crate::{Error, Field};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Matrix {
+    field: Field,
+    n: usize,
+    // row-major.
+    entries: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Structure {
+    // row i is the first row rotated right by i.
+    Circulant,
+    // entry (i, j) is h[i ^ j], for n a power of two.
+    Hadamard,
+}
+
+impl Matrix {
+    pub fn new(n: usize, entries: Vec<u8>) -> Result<Self, Error> {
+        if n == 0 || entries.len() != n * n {
+            return Err(Error::InvalidParameter("a matrix needs n * n entries for some nonzero n"))
+        }
+        Ok(Self { field: Field::AES, n, entries })
+    }
+
+    pub fn identity(n: usize) -> Self {
+        let entries: _ = (0..n * n).map(|idx| (idx / n == idx % n) as u8).collect();
+        Self { field: Field::AES, n, entries }
+    }
+
+    pub fn circulant(row: &[u8]) -> Result<Self, Error> {
+        let n: _ = row.len();
+        let entries: _ = (0..n * n).map(|idx| row[(idx % n + n - idx / n) % n]).collect();
+        Self::new(n, entries)
+    }
+
+    pub fn hadamard(row: &[u8]) -> Result<Self, Error> {
+        let n: _ = row.len();
+        if !n.is_power_of_two() {
+            return Err(Error::InvalidParameter("a Hadamard matrix needs a power of two size"))
+        }
+        let entries: _ = (0..n * n).map(|idx| row[(idx / n) ^ (idx % n)]).collect();
+        Self::new(n, entries)
+    }
+
+    pub fn from_structure(structure: Structure, row: &[u8]) -> Result<Self, Error> {
+        match structure {
+            Structure::Circulant => Self::circulant(row),
+            Structure::Hadamard => Self::hadamard(row),
+        }
+    }
+
+    // the field the entries live in, AES by default.
+    pub fn with_field(mut self, field: Field) -> Self {
+        self.field = field;
+        self
+    }
+
+    pub fn field(&self) -> &Field {
+        &self.field
+    }
+
+    // every entry is an element of the field, which a smaller field than the default may break.
+    pub fn in_field(&self) -> bool {
+        self.entries.iter().all(|val| self.field.contains(*val))
+    }
+
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> u8 {
+        self.entries[row * self.n + col]
+    }
+
+    pub fn apply(&self, vec: &[u8]) -> Vec<u8> {
+        (0..self.n)
+            .map(|row| (0..self.n).fold(0, |acc, col| acc ^ self.field.mul(self.get(row, col), vec[col])))
+            .collect()
+    }
+
+    pub fn mul(&self, rhs: &Matrix) -> Result<Matrix, Error> {
+        if self.n != rhs.n || self.field != rhs.field {
+            return Err(Error::InvalidParameter("the matrices differ in size or field"))
+        }
+        let n: _ = self.n;
+        let entries: _ = (0..n * n)
+            .map(|idx| (0..n).fold(0, |acc, k| acc ^ self.field.mul(self.get(idx / n, k), rhs.get(k, idx % n))))
+            .collect();
+        Ok(Self { field: self.field.clone(), n, entries })
+    }
+
+    pub fn inverse(&self) -> Result<Matrix, Error> {
+        let n: _ = self.n;
+        // Gauss-Jordan on [M | I].
+        let mut rows: Vec<Vec<u8>> = (0..n)
+            .map(|row| {
+                let mut buf: _ = self.entries[row * n..(row + 1) * n].to_vec();
+                buf.extend((0..n).map(|col| (row == col) as u8));
+                buf
+            })
+            .collect();
+        for col in 0..n {
+            let pivot: _ = (col..n)
+                .find(|row| rows[*row][col] != 0)
+                .ok_or(Error::InvalidParameter("the matrix is singular"))?;
+            rows.swap(col, pivot);
+            let scale: _ = self.field.inverse(rows[col][col])?;
+            for val in rows[col].iter_mut() {
+                *val = self.field.mul(*val, scale)
+            }
+            let pivot_row: _ = rows[col].clone();
+            for (idx, row) in rows.iter_mut().enumerate() {
+                let factor: _ = row[col];
+                if idx != col && factor != 0 {
+                    for (val, pivot_val) in row.iter_mut().zip(pivot_row.iter()) {
+                        *val ^= self.field.mul(factor, *pivot_val)
+                    }
+                }
+            }
+        }
+        let entries: _ = rows.into_iter().flat_map(|row| row.into_iter().skip(n)).collect();
+        Ok(Self { field: self.field.clone(), n, entries })
+    }
+
+    // every square submatrix is nonsingular.
+    pub fn is_mds(&self) -> bool {
+        let subsets: _ = 1usize << self.n;
+        (1..subsets).all(|rows| {
+            (1..subsets)
+                .filter(|cols| cols.count_ones() == rows.count_ones())
+                .all(|cols| self.rank(rows, cols) == rows.count_ones() as usize)
+        })
+    }
+
+    // the least wt(x) + wt(Mx) over nonzero x. Some x within columns S has Mx zero on
+    // rows C exactly when the submatrix on C and S has rank below |S|.
+    pub fn branch_number(&self) -> usize {
+        let subsets: _ = 1usize << self.n;
+        let mut best: _ = 2 * self.n;
+        for cols in 1..subsets {
+            for rows in 0..subsets {
+                if self.rank(rows, cols) < cols.count_ones() as usize {
+                    best = best.min(cols.count_ones() as usize + self.n - rows.count_ones() as usize)
+                }
+            }
+        }
+        best
+    }
+
+    // XORs for a direct implementation: each entry's multiplication as an n x n binary
+    // matrix for GF(2^n), plus n XORs for every sum of two nonzero terms in a row.
+    pub fn xor_count(&self) -> usize {
+        let bits: _ = self.field.degree() as usize;
+        self.entries
+            .chunks(self.n)
+            .map(|row| {
+                let terms: _ = row.iter().filter(|val| **val != 0).count();
+                row.iter().map(|val| self.entry_xor_count(*val)).sum::<usize>() + bits * terms.saturating_sub(1)
+            })
+            .sum()
+    }
+
+    fn entry_xor_count(&self, val: u8) -> usize {
+        // column j of the binary matrix is val * x^j, so row i collects bit i of each.
+        let bits: _ = self.field.degree();
+        let cols: Vec<u8> = (0..bits).map(|j| self.field.mul(val, 1 << j)).collect();
+        (0..bits)
+            .map(|i| cols.iter().filter(|col| (*col >> i) & 1 == 1).count().saturating_sub(1))
+            .sum()
+    }
+
+    // the rank of the submatrix on the rows and columns set in the two masks.
+    fn rank(&self, rows: usize, cols: usize) -> usize {
+        let cols: Vec<usize> = (0..self.n).filter(|col| (cols >> col) & 1 == 1).collect();
+        let mut sub: Vec<Vec<u8>> = (0..self.n)
+            .filter(|row| (rows >> row) & 1 == 1)
+            .map(|row| cols.iter().map(|col| self.get(row, *col)).collect())
+            .collect();
+
+        let mut rank: _ = 0;
+        for col in 0..cols.len() {
+            let pivot: _ = match (rank..sub.len()).find(|row| sub[*row][col] != 0) {
+                Some(pivot) => pivot,
+                None => continue,
+            };
+            sub.swap(rank, pivot);
+            let scale: _ = self.field.inverse(sub[rank][col]).expect("attempting to eliminate: the pivot is nonzero.");
+            let pivot_row: _ = sub[rank].clone();
+            for row in sub[rank + 1..].iter_mut() {
+                let factor: _ = self.field.mul(row[col], scale);
+                for (val, pivot_val) in row[col..].iter_mut().zip(pivot_row[col..].iter()) {
+                    *val ^= self.field.mul(factor, *pivot_val)
+                }
+            }
+            rank += 1;
+        }
+        rank
+    }
+}
+
+// the MDS matrix of the given structure and size with the lowest XOR count whose first row
+// is drawn from the entries, or none if no such matrix is MDS.
+pub fn search(structure: Structure, n: usize, entries: &[u8]) -> Result<Option<Matrix>, Error> {
+    if entries.is_empty() {
+        return Err(Error::InvalidParameter("the search needs at least one entry"))
+    }
+    let total: _ = (entries.len() as u64)
+        .checked_pow(n as u32)
+        .ok_or(Error::InvalidParameter("the search space is too large"))?;
+
+    let mut best: Option<(usize, Matrix)> = None;
+    let mut row: _ = vec![0; n];
+    for mut idx in 0..total {
+        for val in row.iter_mut() {
+            *val = entries[(idx % entries.len() as u64) as usize];
+            idx /= entries.len() as u64;
+        }
+        let matrix: _ = Matrix::from_structure(structure, &row)?;
+        let cost: _ = matrix.xor_count();
+        if best.as_ref().is_none_or(|(best_cost, _)| cost < *best_cost) && matrix.is_mds() {
+            best = Some((cost, matrix))
+        }
+    }
+    Ok(best.map(|(_, matrix)| matrix))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mix_columns() -> Matrix {
+        Matrix::circulant(&[0x02, 0x03, 0x01, 0x01]).unwrap()
+    }
+
+    #[test]
+    fn test_mix_columns() {
+        let matrix: _ = mix_columns();
+        assert_eq!(0x03, matrix.get(0, 1));
+        assert_eq!(0x03, matrix.get(3, 0));
+        assert!(matrix.is_mds());
+        assert_eq!(5, matrix.branch_number());
+        // the naive count usually quoted for AES, 4 (3 + 11 + 3 * 8).
+        assert_eq!(152, matrix.xor_count());
+
+        // FIPS-197 appendix B, the first column of round 1.
+        assert_eq!(vec![0x04, 0x66, 0x81, 0xe5], matrix.apply(&[0xd4, 0xbf, 0x5d, 0x30]));
+    }
+
+    #[test]
+    fn test_inverse() {
+        let matrix: _ = mix_columns();
+        let inverse: _ = matrix.inverse().unwrap();
+        assert_eq!(Matrix::circulant(&[0x0e, 0x0b, 0x0d, 0x09]).unwrap(), inverse);
+        assert_eq!(Matrix::identity(4), matrix.mul(&inverse).unwrap());
+
+        let singular: _ = Matrix::circulant(&[0x01, 0x01, 0x00, 0x00]).unwrap();
+        assert!(singular.inverse().is_err());
+        assert!(!singular.is_mds());
+    }
+
+    #[test]
+    fn test_branch_number() {
+        assert_eq!(2, Matrix::identity(4).branch_number());
+        assert!(!Matrix::identity(4).is_mds());
+        // x -> (x0 + x1, x1) keeps a one byte difference in one byte.
+        assert_eq!(2, Matrix::new(2, vec![1, 1, 0, 1]).unwrap().branch_number());
+        assert_eq!(3, Matrix::new(2, vec![1, 1, 1, 2]).unwrap().branch_number());
+    }
+
+    #[test]
+    fn test_hadamard() {
+        // the Anubis diffusion matrix had(1, 2, 4, 6), which is MDS and an involution.
+        let matrix: _ = Matrix::hadamard(&[0x01, 0x02, 0x04, 0x06]).unwrap().with_field(Field::new(0x11d, 0x02).unwrap());
+        assert!(matrix.is_mds());
+        assert_eq!(Ok(matrix.clone()), matrix.inverse());
+        assert!(Matrix::hadamard(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_small_field() {
+        // the LED MixColumnsSerial matrix over GF(16), which is MDS and A^4 for its serial
+        // form A, the companion of (4, 1, 2, 2).
+        let field: _ = Field::new(0x13, 0x02).unwrap();
+        let serial: _ = Matrix::new(4, vec![0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 4, 1, 2, 2]).unwrap().with_field(field.clone());
+        let square: _ = serial.mul(&serial).unwrap();
+        let matrix: _ = Matrix::new(4, vec![4, 1, 2, 2, 8, 6, 5, 6, 11, 14, 10, 9, 2, 2, 15, 11]).unwrap().with_field(field);
+        assert_eq!(Ok(matrix.clone()), square.mul(&square));
+        assert!(matrix.in_field());
+        assert!(matrix.is_mds());
+        assert_eq!(Ok(Matrix::identity(4).with_field(matrix.field().clone())), matrix.mul(&matrix.inverse().unwrap()));
+        assert!(!Matrix::circulant(&[0x02, 0x1b, 0x01, 0x01]).unwrap().with_field(Field::new(0x13, 0x02).unwrap()).in_field());
+    }
+
+    #[test]
+    fn test_search() {
+        // no circulant over {1, 2} is MDS, and {1, 2, 3} finds one costing what AES does.
+        assert_eq!(Ok(None), search(Structure::Circulant, 4, &[0x01, 0x02]));
+        let best: _ = search(Structure::Circulant, 4, &[0x01, 0x02, 0x03]).unwrap().unwrap();
+        assert!(best.is_mds());
+        assert_eq!(mix_columns().xor_count(), best.xor_count());
+        assert!(search(Structure::Hadamard, 4, &[]).is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{to_block, xor_block, Block, BLOCK_SIZE, Error, RjindaelCipher};
+
+#[derive(Debug, Clone)]
+pub struct Cbc {
+    cipher: RjindaelCipher,
+    iv: Block,
+}
+
+impl Cbc {
+    pub fn new(cipher: RjindaelCipher, iv: Block) -> Self {
+        Self { cipher, iv }
+    }
+
+    pub fn encrypt(&self, buf: &mut [u8]) -> Result<(), Error> {
+        check_aligned(buf)?;
+        let mut prev: Block = self.iv;
+        for chunk in buf.chunks_mut(BLOCK_SIZE) {
+            xor_block(&mut prev, &to_block(chunk));
+            self.cipher.encrypt_block(&mut prev);
+            chunk.copy_from_slice(&prev);
+        }
+        Ok(())
+    }
+
+    pub fn decrypt(&self, buf: &mut [u8]) -> Result<(), Error> {
+        self.decrypt_from(self.iv, buf)
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn iv(&self) -> Block {
+        self.iv
+    }
+
+    // decrypts a run of blocks whose preceding ciphertext block is prev.
+    pub(crate) fn decrypt_from(&self, mut prev: Block, buf: &mut [u8]) -> Result<(), Error> {
+        check_aligned(buf)?;
+        for chunk in buf.chunks_mut(BLOCK_SIZE) {
+            let cipher_block: Block = to_block(chunk);
+            let mut plain_block: Block = cipher_block;
+            self.cipher.decrypt_block(&mut plain_block);
+            xor_block(&mut plain_block, &prev);
+            chunk.copy_from_slice(&plain_block);
+            prev = cipher_block;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn check_aligned(buf: &[u8]) -> Result<(), Error> {
+    match buf.len() % BLOCK_SIZE {
+        0 => Ok(()),
+        _ => Err(Error::UnalignedInput(buf.len()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // SP 800-38A F.2.1 and F.2.2
+    const PLAIN: [u8; 64] = [
+        0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a,
+        0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac, 0x45, 0xaf, 0x8e, 0x51,
+        0x30, 0xc8, 0x1c, 0x46, 0xa3, 0x5c, 0xe4, 0x11, 0xe5, 0xfb, 0xc1, 0x19, 0x1a, 0x0a, 0x52, 0xef,
+        0xf6, 0x9f, 0x24, 0x45, 0xdf, 0x4f, 0x9b, 0x17, 0xad, 0x2b, 0x41, 0x7b, 0xe6, 0x6c, 0x37, 0x10
+    ];
+
+    const CIPHER: [u8; 64] = [
+        0x76, 0x49, 0xab, 0xac, 0x81, 0x19, 0xb2, 0x46, 0xce, 0xe9, 0x8e, 0x9b, 0x12, 0xe9, 0x19, 0x7d,
+        0x50, 0x86, 0xcb, 0x9b, 0x50, 0x72, 0x19, 0xee, 0x95, 0xdb, 0x11, 0x3a, 0x91, 0x76, 0x78, 0xb2,
+        0x73, 0xbe, 0xd6, 0xb8, 0xe3, 0xc1, 0x74, 0x3b, 0x71, 0x16, 0xe6, 0x9e, 0x22, 0x22, 0x95, 0x16,
+        0x3f, 0xf1, 0xca, 0xa1, 0x68, 0x1f, 0xac, 0x09, 0x12, 0x0e, 0xca, 0x30, 0x75, 0x86, 0xe1, 0xa7
+    ];
+
+    fn test_cbc() -> Cbc {
+        let cipher: _ = RjindaelCipher::new([
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6,
+            0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c
+        ]);
+        let iv: Block = [
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+            0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f
+        ];
+        Cbc::new(cipher, iv)
+    }
+
+    #[test]
+    fn test_encrypt() {
+        let mut buf: _ = PLAIN;
+        test_cbc().encrypt(&mut buf).unwrap();
+        assert_eq!(CIPHER[..], buf[..])
+    }
+
+    #[test]
+    fn test_decrypt() {
+        let mut buf: _ = CIPHER;
+        test_cbc().decrypt(&mut buf).unwrap();
+        assert_eq!(PLAIN[..], buf[..])
+    }
+
+    #[test]
+    fn test_unaligned() {
+        let mut buf: _ = [0; 17];
+        assert_eq!(Err(Error::UnalignedInput(17)), test_cbc().encrypt(&mut buf));
+        assert_eq!(Err(Error::UnalignedInput(17)), test_cbc().decrypt(&mut buf));
+    }
+}
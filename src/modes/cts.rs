@@ -0,0 +1,193 @@
+//! CBC ciphertext stealing from the addendum to SP 800-38A.
+//! The ciphertext keeps the length of the plaintext for any input of at least one block.
+//! CS1 leaves the partial block before the final block, CS3 always swaps the final two
+//! blocks as Kerberos does (RFC 3962), and CS2 swaps them only when the last block is partial.
+
+use crate::{modes, to_block, xor_block, Block, BLOCK_SIZE, Error, RjindaelCipher};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Cs1,
+    Cs2,
+    Cs3,
+}
+
+impl Variant {
+    fn swaps(self, partial_len: usize) -> bool {
+        match self {
+            Variant::Cs1 => false,
+            Variant::Cs2 => partial_len != BLOCK_SIZE,
+            Variant::Cs3 => true
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CbcCts {
+    cipher: RjindaelCipher,
+    iv: Block,
+    variant: Variant,
+}
+
+impl CbcCts {
+    pub fn new(cipher: RjindaelCipher, iv: Block, variant: Variant) -> Self {
+        Self { cipher, iv, variant }
+    }
+
+    pub fn encrypt(&self, buf: &mut [u8]) -> Result<(), Error> {
+        let (partial_len, split): _ = split(buf)?;
+        if split == 0 {
+            return self.cbc().encrypt(buf)
+        }
+
+        let mut last: Block = [0; BLOCK_SIZE];
+        last[..partial_len].copy_from_slice(&buf[split..]);
+        self.cbc().encrypt(&mut buf[..split])?;
+
+        // the zero padded final block is chained as in CBC, then the padding is stolen back.
+        let tail: _ = &mut buf[split - BLOCK_SIZE..];
+        let penultimate: Block = to_block(&tail[..BLOCK_SIZE]);
+        let mut ultimate: Block = penultimate;
+        xor_block(&mut ultimate, &last);
+        self.cipher.encrypt_block(&mut ultimate);
+
+        tail[partial_len..].copy_from_slice(&ultimate);
+        if self.variant.swaps(partial_len) {
+            tail.rotate_left(partial_len)
+        }
+        Ok(())
+    }
+
+    pub fn decrypt(&self, buf: &mut [u8]) -> Result<(), Error> {
+        let (partial_len, split): _ = split(buf)?;
+        if split == 0 {
+            return self.cbc().decrypt(buf)
+        }
+
+        let prev: Block = match split - BLOCK_SIZE {
+            0 => self.iv,
+            idx => to_block(&buf[idx - BLOCK_SIZE..idx])
+        };
+        let tail: _ = &mut buf[split - BLOCK_SIZE..];
+        if self.variant.swaps(partial_len) {
+            tail.rotate_right(partial_len)
+        }
+
+        // the padding stolen from the penultimate block is recovered from the final block.
+        let mut ultimate: Block = to_block(&tail[partial_len..]);
+        self.cipher.decrypt_block(&mut ultimate);
+        let mut penultimate: Block = ultimate;
+        penultimate[..partial_len].copy_from_slice(&tail[..partial_len]);
+        xor_block(&mut ultimate, &penultimate);
+
+        let mut plain: Block = penultimate;
+        self.cipher.decrypt_block(&mut plain);
+        xor_block(&mut plain, &prev);
+
+        tail[..BLOCK_SIZE].copy_from_slice(&plain);
+        tail[BLOCK_SIZE..].copy_from_slice(&ultimate[..partial_len]);
+        self.cbc().decrypt(&mut buf[..split - BLOCK_SIZE])
+    }
+
+    fn cbc(&self) -> modes::Cbc {
+        modes::Cbc::new(self.cipher.clone(), self.iv)
+    }
+}
+
+// returns the length of the final, possibly partial, block and where it starts.
+fn split(buf: &[u8]) -> Result<(usize, usize), Error> {
+    if buf.len() < BLOCK_SIZE {
+        return Err(Error::InputTooShort { len: buf.len(), min: BLOCK_SIZE })
+    }
+    let partial_len: _ = match buf.len() % BLOCK_SIZE {
+        0 => BLOCK_SIZE,
+        rem => rem
+    };
+    Ok((partial_len, buf.len() - partial_len))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::unhex;
+
+    const PLAIN: &[u8; 64] = b"I would like the General Gau's Chicken, please, and wonton soup.";
+
+    fn test_cts(variant: Variant) -> CbcCts {
+        CbcCts::new(RjindaelCipher::new(*b"chicken teriyaki"), [0; BLOCK_SIZE], variant)
+    }
+
+    // RFC 3962 appendix B, AES-128 with a zero IV.
+    #[test]
+    fn test_cs3_vectors() {
+        let cases: [(usize, &str); 6] = [
+            (17, "c6353568f2bf8cb4d8a580362da7ff7f97"),
+            (31, "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5"),
+            (32, "39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584"),
+            (47, "97687268d6ecccc0c07b25e25ecfe584b3fffd940c16a18c1b5549d2f838029e39312523a78662d5be7fcbcc98ebf5"),
+            (48, "97687268d6ecccc0c07b25e25ecfe5849dad8bbb96c4cdc03bc103e1a194bbd839312523a78662d5be7fcbcc98ebf5a8"),
+            (64, "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a84807efe836ee89a526730dbc2f7bc8409dad8bbb96c4cdc03bc103e1a194bbd8"),
+        ];
+        let cts: _ = test_cts(Variant::Cs3);
+
+        for (len, hex) in cases.iter() {
+            let mut buf: _ = PLAIN[..*len].to_vec();
+            cts.encrypt(&mut buf).unwrap();
+            assert_eq!(unhex(hex), buf);
+
+            cts.decrypt(&mut buf).unwrap();
+            assert_eq!(PLAIN[..*len], buf[..]);
+        }
+    }
+
+    #[test]
+    fn test_cs1_and_cs2_layout() {
+        // 31 bytes: CS3 gives C2 || C1*, CS1 keeps C1* in front of C2 and CS2 swaps like CS3.
+        let cs3: _ = unhex("fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5");
+        let mut cs1: _ = cs3[BLOCK_SIZE..].to_vec();
+        cs1.extend_from_slice(&cs3[..BLOCK_SIZE]);
+
+        let mut buf: _ = PLAIN[..31].to_vec();
+        test_cts(Variant::Cs1).encrypt(&mut buf).unwrap();
+        assert_eq!(cs1, buf);
+
+        let mut buf: _ = PLAIN[..31].to_vec();
+        test_cts(Variant::Cs2).encrypt(&mut buf).unwrap();
+        assert_eq!(cs3, buf);
+    }
+
+    #[test]
+    fn test_whole_blocks_match_cbc() {
+        let mut exp: _ = PLAIN.to_vec();
+        modes::Cbc::new(RjindaelCipher::new(*b"chicken teriyaki"), [0; BLOCK_SIZE])
+            .encrypt(&mut exp)
+            .unwrap();
+
+        for variant in [Variant::Cs1, Variant::Cs2].iter() {
+            let mut buf: _ = PLAIN.to_vec();
+            test_cts(*variant).encrypt(&mut buf).unwrap();
+            assert_eq!(exp, buf)
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for variant in [Variant::Cs1, Variant::Cs2, Variant::Cs3].iter() {
+            let cts: _ = test_cts(*variant);
+            for len in 16..=PLAIN.len() {
+                let mut buf: _ = PLAIN[..len].to_vec();
+                cts.encrypt(&mut buf).unwrap();
+                cts.decrypt(&mut buf).unwrap();
+                assert_eq!(PLAIN[..len], buf[..], "{:?} at {} bytes", variant, len)
+            }
+        }
+    }
+
+    #[test]
+    fn test_too_short() {
+        let mut buf: _ = [0; 15];
+        let exp: _ = Err(Error::InputTooShort { len: 15, min: 16 });
+        assert_eq!(exp, test_cts(Variant::Cs3).encrypt(&mut buf));
+        assert_eq!(exp, test_cts(Variant::Cs3).decrypt(&mut buf));
+    }
+}
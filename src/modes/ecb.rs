@@ -0,0 +1,57 @@
+use crate::{modes::cbc::check_aligned, to_block, Block, BLOCK_SIZE, Error, RjindaelCipher};
+
+// electronic codebook, each block on its own. Only for independent random blocks such as keys.
+#[derive(Debug, Clone)]
+pub struct Ecb {
+    cipher: RjindaelCipher,
+}
+
+impl Ecb {
+    pub fn new(cipher: RjindaelCipher) -> Self {
+        Self { cipher }
+    }
+
+    pub fn encrypt(&self, buf: &mut [u8]) -> Result<(), Error> {
+        check_aligned(buf)?;
+        for chunk in buf.chunks_mut(BLOCK_SIZE) {
+            let mut block: Block = to_block(chunk);
+            self.cipher.encrypt_block(&mut block);
+            chunk.copy_from_slice(&block);
+        }
+        Ok(())
+    }
+
+    pub fn decrypt(&self, buf: &mut [u8]) -> Result<(), Error> {
+        check_aligned(buf)?;
+        for chunk in buf.chunks_mut(BLOCK_SIZE) {
+            let mut block: Block = to_block(chunk);
+            self.cipher.decrypt_block(&mut block);
+            chunk.copy_from_slice(&block);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::unhex;
+
+    // SP 800-38A F.1.1 and F.1.2, the first two blocks.
+    #[test]
+    fn test_ecb() {
+        let ecb: _ = Ecb::new(RjindaelCipher::new([
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6,
+            0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c
+        ]));
+        let plain: _ = unhex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51");
+        let exp: _ = unhex("3ad77bb40d7a3660a89ecaf32466ef97f5d3d58503b9699de785895a96fdbaaf");
+
+        let mut test: _ = plain.clone();
+        ecb.encrypt(&mut test).unwrap();
+        assert_eq!(exp, test);
+        ecb.decrypt(&mut test).unwrap();
+        assert_eq!(plain, test);
+        assert_eq!(Err(Error::UnalignedInput(17)), ecb.encrypt(&mut [0; 17]));
+    }
+}
@@ -0,0 +1,116 @@
+//! Infinite Garble Extension, as used by OpenSSL's AES_ige_encrypt and MTProto.
+//! Each block is chained to both the previous ciphertext and the previous plaintext:
+//! c_i = E(m_i ^ c_{i-1}) ^ m_{i-1}. The IV holds c_0 followed by m_0.
+
+use crate::{to_block, xor_block, Block, BLOCK_SIZE, Error, RjindaelCipher};
+use crate::modes::cbc::check_aligned;
+
+pub const IGE_IV_SIZE: usize = 2 * BLOCK_SIZE;
+
+#[derive(Debug, Clone)]
+pub struct Ige {
+    cipher: RjindaelCipher,
+    iv: [u8; IGE_IV_SIZE],
+}
+
+impl Ige {
+    pub fn new(cipher: RjindaelCipher, iv: [u8; IGE_IV_SIZE]) -> Self {
+        Self { cipher, iv }
+    }
+
+    pub fn encrypt(&self, buf: &mut [u8]) -> Result<(), Error> {
+        check_aligned(buf)?;
+        let (mut prev_cipher, mut prev_plain): _ = self.split_iv();
+        for chunk in buf.chunks_mut(BLOCK_SIZE) {
+            let plain: Block = to_block(chunk);
+            let mut block: Block = plain;
+            xor_block(&mut block, &prev_cipher);
+            self.cipher.encrypt_block(&mut block);
+            xor_block(&mut block, &prev_plain);
+
+            chunk.copy_from_slice(&block);
+            prev_cipher = block;
+            prev_plain = plain;
+        }
+        Ok(())
+    }
+
+    pub fn decrypt(&self, buf: &mut [u8]) -> Result<(), Error> {
+        check_aligned(buf)?;
+        let (mut prev_cipher, mut prev_plain): _ = self.split_iv();
+        for chunk in buf.chunks_mut(BLOCK_SIZE) {
+            let cipher_block: Block = to_block(chunk);
+            let mut block: Block = cipher_block;
+            xor_block(&mut block, &prev_plain);
+            self.cipher.decrypt_block(&mut block);
+            xor_block(&mut block, &prev_cipher);
+
+            chunk.copy_from_slice(&block);
+            prev_cipher = cipher_block;
+            prev_plain = block;
+        }
+        Ok(())
+    }
+
+    fn split_iv(&self) -> (Block, Block) {
+        (to_block(&self.iv[..BLOCK_SIZE]), to_block(&self.iv[BLOCK_SIZE..]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::unhex;
+
+    fn to_iv(bytes: &[u8]) -> [u8; IGE_IV_SIZE] {
+        let mut iv: [u8; IGE_IV_SIZE] = [0; IGE_IV_SIZE];
+        iv.copy_from_slice(bytes);
+        iv
+    }
+
+    // the vectors published with the OpenSSL IGE implementation (test/igetest.c).
+    #[test]
+    fn test_published_vectors() {
+        let cases: [(&str, &str, &str, &str); 2] = [
+            (
+                "000102030405060708090a0b0c0d0e0f",
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "1a8519a6557be652e9da8e43da4ef4453cf456b4ca488aa383c79c98b34797cb"
+            ),
+            (
+                "5468697320697320616e20696d706c65",
+                "6d656e746174696f6e206f6620494745206d6f646520666f72204f70656e5353",
+                "99706487a1cde613bc6de0b6f24b1c7aa448c8b9c3403e3467a8cad89340f53b",
+                "4c2e204c6574277320686f70652042656e20676f74206974207269676874210a"
+            ),
+        ];
+
+        for (key, iv, plain, exp) in cases.iter() {
+            let ige: _ = Ige::new(RjindaelCipher::new(to_block(&unhex(key))), to_iv(&unhex(iv)));
+            let mut buf: _ = unhex(plain);
+            ige.encrypt(&mut buf).unwrap();
+            assert_eq!(unhex(exp), buf);
+
+            ige.decrypt(&mut buf).unwrap();
+            assert_eq!(unhex(plain), buf);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let ige: _ = Ige::new(RjindaelCipher::new([0x42; BLOCK_SIZE]), to_iv(&[0x24; IGE_IV_SIZE]));
+        let plain: Vec<u8> = (0..80).collect();
+        let mut buf: _ = plain.clone();
+        ige.encrypt(&mut buf).unwrap();
+        assert_ne!(plain, buf);
+        ige.decrypt(&mut buf).unwrap();
+        assert_eq!(plain, buf)
+    }
+
+    #[test]
+    fn test_unaligned() {
+        let ige: _ = Ige::new(RjindaelCipher::new([0; BLOCK_SIZE]), [0; IGE_IV_SIZE]);
+        assert_eq!(Err(Error::UnalignedInput(20)), ige.encrypt(&mut [0; 20]));
+    }
+}
@@ -0,0 +1,15 @@
+pub(crate) mod cbc;
+mod cts;
+mod ctr;
+mod ecb;
+mod ige;
+mod pcbc;
+mod xts;
+
+pub use cbc::Cbc;
+pub use cts::{CbcCts, Variant};
+pub use ctr::Ctr;
+pub use ecb::Ecb;
+pub use ige::{Ige, IGE_IV_SIZE};
+pub use pcbc::Pcbc;
+pub use xts::Xts;
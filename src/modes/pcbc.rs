@@ -0,0 +1,111 @@
+//! Propagating CBC, as used by Kerberos v4 and WASTE.
+//! Each block is chained to the XOR of the previous plaintext and ciphertext:
+//! c_i = E(m_i ^ m_{i-1} ^ c_{i-1}) with m_0 ^ c_0 taken as the IV.
+
+use crate::{to_block, xor_block, Block, BLOCK_SIZE, Error, RjindaelCipher};
+use crate::modes::cbc::check_aligned;
+
+#[derive(Debug, Clone)]
+pub struct Pcbc {
+    cipher: RjindaelCipher,
+    iv: Block,
+}
+
+impl Pcbc {
+    pub fn new(cipher: RjindaelCipher, iv: Block) -> Self {
+        Self { cipher, iv }
+    }
+
+    pub fn encrypt(&self, buf: &mut [u8]) -> Result<(), Error> {
+        check_aligned(buf)?;
+        let mut prev: Block = self.iv;
+        for chunk in buf.chunks_mut(BLOCK_SIZE) {
+            let plain: Block = to_block(chunk);
+            xor_block(&mut prev, &plain);
+            self.cipher.encrypt_block(&mut prev);
+            chunk.copy_from_slice(&prev);
+            xor_block(&mut prev, &plain);
+        }
+        Ok(())
+    }
+
+    pub fn decrypt(&self, buf: &mut [u8]) -> Result<(), Error> {
+        check_aligned(buf)?;
+        let mut prev: Block = self.iv;
+        for chunk in buf.chunks_mut(BLOCK_SIZE) {
+            let cipher_block: Block = to_block(chunk);
+            let mut block: Block = cipher_block;
+            self.cipher.decrypt_block(&mut block);
+            xor_block(&mut block, &prev);
+            chunk.copy_from_slice(&block);
+
+            prev = cipher_block;
+            xor_block(&mut prev, &block);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::modes;
+
+    // SP 800-38A F.2.1 key, IV and plaintext.
+    const PLAIN: [u8; 64] = [
+        0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a,
+        0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac, 0x45, 0xaf, 0x8e, 0x51,
+        0x30, 0xc8, 0x1c, 0x46, 0xa3, 0x5c, 0xe4, 0x11, 0xe5, 0xfb, 0xc1, 0x19, 0x1a, 0x0a, 0x52, 0xef,
+        0xf6, 0x9f, 0x24, 0x45, 0xdf, 0x4f, 0x9b, 0x17, 0xad, 0x2b, 0x41, 0x7b, 0xe6, 0x6c, 0x37, 0x10
+    ];
+
+    // cross-checked against PCBC built on an independent AES implementation.
+    const CIPHER: [u8; 64] = [
+        0x76, 0x49, 0xab, 0xac, 0x81, 0x19, 0xb2, 0x46, 0xce, 0xe9, 0x8e, 0x9b, 0x12, 0xe9, 0x19, 0x7d,
+        0x9e, 0x8b, 0xaf, 0xf1, 0x2a, 0xd5, 0x27, 0x0a, 0x0d, 0x1e, 0xef, 0x93, 0xd7, 0x03, 0x79, 0x94,
+        0x57, 0x00, 0xb3, 0x98, 0x03, 0x77, 0x9f, 0xa3, 0x5a, 0x3c, 0x60, 0x0a, 0x49, 0xa1, 0x63, 0xc0,
+        0x33, 0xae, 0x19, 0x9f, 0x27, 0x37, 0x9f, 0x21, 0xbe, 0x6d, 0xd5, 0x7d, 0x29, 0x5c, 0xc8, 0x7d
+    ];
+
+    fn test_cipher() -> RjindaelCipher {
+        RjindaelCipher::new([
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6,
+            0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c
+        ])
+    }
+
+    const IV: Block = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+        0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f
+    ];
+
+    #[test]
+    fn test_encrypt() {
+        let mut buf: _ = PLAIN;
+        Pcbc::new(test_cipher(), IV).encrypt(&mut buf).unwrap();
+        assert_eq!(CIPHER[..], buf[..]);
+
+        // the first block is plain CBC.
+        let mut cbc: _ = PLAIN;
+        modes::Cbc::new(test_cipher(), IV).encrypt(&mut cbc).unwrap();
+        assert_eq!(cbc[..BLOCK_SIZE], buf[..BLOCK_SIZE]);
+    }
+
+    #[test]
+    fn test_decrypt() {
+        let mut buf: _ = CIPHER;
+        Pcbc::new(test_cipher(), IV).decrypt(&mut buf).unwrap();
+        assert_eq!(PLAIN[..], buf[..])
+    }
+
+    #[test]
+    fn test_error_propagates() {
+        // a corrupted block garbles every following block, unlike CBC.
+        let mut buf: _ = CIPHER;
+        buf[0] ^= 0x01;
+        Pcbc::new(test_cipher(), IV).decrypt(&mut buf).unwrap();
+        for (plain, test) in PLAIN.chunks(BLOCK_SIZE).zip(buf.chunks(BLOCK_SIZE)) {
+            assert_ne!(plain, test)
+        }
+    }
+}
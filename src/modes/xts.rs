@@ -0,0 +1,134 @@
+//! XTS-AES-128 from IEEE 1619, encrypting one data unit at a time under a pair of keys.
+//! A data unit that is not a whole number of blocks borrows from its last full block.
+
+use crate::{to_block, xor_block, Block, BLOCK_SIZE, Error, RjindaelCipher};
+
+#[derive(Debug, Clone)]
+pub struct Xts {
+    cipher: RjindaelCipher,
+    tweak_cipher: RjindaelCipher,
+}
+
+impl Xts {
+    // the data key, then the tweak key; IEEE 1619 requires them to differ.
+    pub fn new(cipher: RjindaelCipher, tweak_cipher: RjindaelCipher) -> Self {
+        Self { cipher, tweak_cipher }
+    }
+
+    pub fn encrypt(&self, unit: u128, buf: &mut [u8]) -> Result<(), Error> {
+        self.process(unit, buf, true)
+    }
+
+    pub fn decrypt(&self, unit: u128, buf: &mut [u8]) -> Result<(), Error> {
+        self.process(unit, buf, false)
+    }
+
+    fn process(&self, unit: u128, buf: &mut [u8], encrypt: bool) -> Result<(), Error> {
+        if buf.len() < BLOCK_SIZE {
+            return Err(Error::InputTooShort { len: buf.len(), min: BLOCK_SIZE })
+        }
+        let mut tweak: Block = unit.to_le_bytes();
+        self.tweak_cipher.encrypt_block(&mut tweak);
+
+        let full: _ = buf.len() / BLOCK_SIZE;
+        let rem: _ = buf.len() % BLOCK_SIZE;
+        let plain_full: _ = match rem { 0 => full, _ => full - 1 };
+        for chunk in buf[..plain_full * BLOCK_SIZE].chunks_mut(BLOCK_SIZE) {
+            chunk.copy_from_slice(&self.block(chunk, &tweak, encrypt));
+            tweak = mul_alpha(&tweak);
+        }
+        if rem == 0 {
+            return Ok(())
+        }
+
+        // ciphertext stealing: decryption takes the two remaining tweaks in the other order.
+        let next: _ = mul_alpha(&tweak);
+        let (first, second): _ = match encrypt {
+            true => (tweak, next),
+            false => (next, tweak)
+        };
+        let tail: _ = &mut buf[plain_full * BLOCK_SIZE..];
+        let mut stolen: _ = self.block(&tail[..BLOCK_SIZE], &first, encrypt);
+        let mut last: Block = stolen;
+        last[..rem].copy_from_slice(&tail[BLOCK_SIZE..]);
+        tail[BLOCK_SIZE..].copy_from_slice(&stolen[..rem]);
+        stolen = self.block(&last, &second, encrypt);
+        tail[..BLOCK_SIZE].copy_from_slice(&stolen);
+        Ok(())
+    }
+
+    fn block(&self, chunk: &[u8], tweak: &Block, encrypt: bool) -> Block {
+        let mut block: Block = to_block(chunk);
+        xor_block(&mut block, tweak);
+        match encrypt {
+            true => self.cipher.encrypt_block(&mut block),
+            false => self.cipher.decrypt_block(&mut block)
+        }
+        xor_block(&mut block, tweak);
+        block
+    }
+}
+
+// multiplication by x in GF(2^128), with the little-endian byte order of IEEE 1619.
+fn mul_alpha(tweak: &Block) -> Block {
+    let val: _ = u128::from_le_bytes(*tweak);
+    let carry: _ = (val >> 127) as u8;
+    let mut buf: Block = (val << 1).to_le_bytes();
+    buf[0] ^= 0x87 * carry;
+    buf
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::unhex;
+
+    fn test_xts() -> Xts {
+        Xts::new(
+            RjindaelCipher::new([
+                0xff, 0xfe, 0xfd, 0xfc, 0xfb, 0xfa, 0xf9, 0xf8,
+                0xf7, 0xf6, 0xf5, 0xf4, 0xf3, 0xf2, 0xf1, 0xf0
+            ]),
+            RjindaelCipher::new([
+                0xbf, 0xbe, 0xbd, 0xbc, 0xbb, 0xba, 0xb9, 0xb8,
+                0xb7, 0xb6, 0xb5, 0xb4, 0xb3, 0xb2, 0xb1, 0xb0
+            ])
+        )
+    }
+
+    // IEEE 1619 vectors 1 and 2
+    #[test]
+    fn test_ieee_vectors() {
+        let xts: _ = Xts::new(RjindaelCipher::new([0; 16]), RjindaelCipher::new([0; 16]));
+        let mut test: _ = [0; 32];
+        xts.encrypt(0, &mut test).unwrap();
+        assert_eq!(unhex("917cf69ebd68b2ec9b9fe9a3eadda692cd43d2f59598ed858c02c2652fbf922e"), test.to_vec());
+
+        let xts: _ = Xts::new(RjindaelCipher::new([0x11; 16]), RjindaelCipher::new([0x22; 16]));
+        let mut test: _ = [0x44; 32];
+        xts.encrypt(0x3333333333, &mut test).unwrap();
+        assert_eq!(unhex("c454185e6a16936e39334038acef838bfb186fff7480adc4289382ecd6d394f0"), test.to_vec());
+        xts.decrypt(0x3333333333, &mut test).unwrap();
+        assert_eq!([0x44; 32], test);
+    }
+
+    // partial data units, cross-checked against pyca cryptography.
+    #[test]
+    fn test_stealing() {
+        let cases: [(u128, usize, &str); 3] = [
+            (0x9a78563412, 17, "641610679dcbf92e505c41333fb06c2a95"),
+            (0x9a78563412, 20, "a8ba0048d75084603eb8423a09b7bf7595c871f6"),
+            (7, 37, "2b50204d59ce3711ac622b9daae65f83b1e77a06bb1d4e3b8848e93a5ed9beac0fcf1a92b4"),
+        ];
+        let xts: _ = test_xts();
+        for (unit, len, exp) in cases.iter() {
+            let plain: Vec<u8> = (0..*len as u8).collect();
+            let mut test: _ = plain.clone();
+            xts.encrypt(*unit, &mut test).unwrap();
+            assert_eq!(unhex(exp), test);
+            xts.decrypt(*unit, &mut test).unwrap();
+            assert_eq!(plain, test);
+        }
+        assert_eq!(Err(Error::InputTooShort { len: 15, min: 16 }), xts.encrypt(0, &mut [0; 15]));
+    }
+}
@@ -0,0 +1,201 @@
+//! Splits the parallelizable modes across std::thread workers. Each worker clones the mode,
+//! and with it the expanded key, so no state is shared while a buffer is processed.
+//! Chunks always fall on block or data unit boundaries, so the output does not depend
+//! on the number of workers.
+
+use std::thread;
+
+use crate::{modes, modes::cbc::check_aligned, to_block, Block, BLOCK_SIZE, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parallel {
+    threads: usize,
+}
+
+impl Parallel {
+    pub fn new(threads: usize) -> Self {
+        Self { threads: threads.max(1) }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    pub fn ecb_encrypt(&self, ecb: &modes::Ecb, buf: &mut [u8]) -> Result<(), Error> {
+        check_aligned(buf)?;
+        self.split(buf, BLOCK_SIZE, ecb, |ecb, _, chunk| ecb.encrypt(chunk))
+    }
+
+    pub fn ecb_decrypt(&self, ecb: &modes::Ecb, buf: &mut [u8]) -> Result<(), Error> {
+        check_aligned(buf)?;
+        self.split(buf, BLOCK_SIZE, ecb, |ecb, _, chunk| ecb.decrypt(chunk))
+    }
+
+    pub fn ctr_apply_keystream(&self, ctr: &modes::Ctr, buf: &mut [u8]) {
+        self.split(buf, BLOCK_SIZE, ctr, |ctr, offset, chunk| {
+            ctr.apply_keystream_at(offset as u64, chunk);
+            Ok(())
+        })
+        .expect("attempting to apply a keystream: counter mode cannot fail.")
+    }
+
+    // every chunk after the first chains from the ciphertext block before it, read up front.
+    pub fn cbc_decrypt(&self, cbc: &modes::Cbc, buf: &mut [u8]) -> Result<(), Error> {
+        check_aligned(buf)?;
+        let len: _ = self.chunk_len(buf.len(), BLOCK_SIZE);
+        let prevs: Vec<Block> = (0..buf.len()).step_by(len.max(1))
+            .map(|offset| match offset {
+                0 => cbc.iv(),
+                _ => to_block(&buf[offset - BLOCK_SIZE..offset])
+            })
+            .collect();
+        self.split(buf, BLOCK_SIZE, cbc, |cbc, offset, chunk| cbc.decrypt_from(prevs[offset / len], chunk))
+    }
+
+    // consecutive data units of unit_size bytes, numbered from first_unit; the last may be shorter.
+    pub fn xts_encrypt(&self, xts: &modes::Xts, first_unit: u128, unit_size: usize, buf: &mut [u8]) -> Result<(), Error> {
+        self.xts(xts, first_unit, unit_size, buf, modes::Xts::encrypt)
+    }
+
+    pub fn xts_decrypt(&self, xts: &modes::Xts, first_unit: u128, unit_size: usize, buf: &mut [u8]) -> Result<(), Error> {
+        self.xts(xts, first_unit, unit_size, buf, modes::Xts::decrypt)
+    }
+
+    fn xts<F>(&self, xts: &modes::Xts, first_unit: u128, unit_size: usize, buf: &mut [u8], op: F) -> Result<(), Error>
+    where
+        F: Fn(&modes::Xts, u128, &mut [u8]) -> Result<(), Error> + Sync
+    {
+        if unit_size < BLOCK_SIZE {
+            return Err(Error::InvalidParameter("an XTS data unit holds at least one block"))
+        }
+        self.split(buf, unit_size, xts, |xts, offset, chunk| {
+            let first: _ = first_unit + (offset / unit_size) as u128;
+            chunk.chunks_mut(unit_size)
+                .enumerate()
+                .try_for_each(|(idx, unit)| op(xts, first + idx as u128, unit))
+        })
+    }
+
+    // whole units per worker, spread as evenly as the units allow.
+    fn chunk_len(&self, len: usize, unit: usize) -> usize {
+        len.div_ceil(unit).div_ceil(self.threads) * unit
+    }
+
+    // runs op on each chunk with its own clone of the mode and the chunk's byte offset.
+    fn split<M, F>(&self, buf: &mut [u8], unit: usize, mode: &M, op: F) -> Result<(), Error>
+    where
+        M: Clone + Send,
+        F: Fn(&M, usize, &mut [u8]) -> Result<(), Error> + Sync
+    {
+        let len: _ = self.chunk_len(buf.len(), unit);
+        if len == 0 {
+            return Ok(())
+        }
+        if self.threads == 1 {
+            return op(mode, 0, buf)
+        }
+
+        thread::scope(|scope| {
+            let workers: Vec<_> = buf.chunks_mut(len)
+                .enumerate()
+                .map(|(idx, chunk)| {
+                    let (op, mode): _ = (&op, mode.clone());
+                    scope.spawn(move || op(&mode, idx * len, chunk))
+                })
+                .collect();
+            workers.into_iter()
+                .try_for_each(|worker| worker.join().expect("attempting to join a worker thread that panicked."))
+        })
+    }
+}
+
+impl Default for Parallel {
+    // one worker per available core.
+    fn default() -> Self {
+        Self::new(thread::available_parallelism().map_or(1, |count| count.get()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::RjindaelCipher;
+
+    fn cipher() -> RjindaelCipher {
+        RjindaelCipher::new(*b"parallel key 012")
+    }
+
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|x| (x * 7) as u8).collect()
+    }
+
+    const THREADS: [usize; 4] = [1, 2, 3, 8];
+
+    #[test]
+    fn test_ecb() {
+        let ecb: _ = modes::Ecb::new(cipher());
+        let mut exp: _ = message(16 * 37);
+        ecb.encrypt(&mut exp).unwrap();
+        for threads in THREADS.iter() {
+            let mut test: _ = message(16 * 37);
+            Parallel::new(*threads).ecb_encrypt(&ecb, &mut test).unwrap();
+            assert_eq!(exp, test);
+            Parallel::new(*threads).ecb_decrypt(&ecb, &mut test).unwrap();
+            assert_eq!(message(16 * 37), test);
+        }
+        assert_eq!(Err(Error::UnalignedInput(17)), Parallel::new(2).ecb_encrypt(&ecb, &mut [0; 17]));
+    }
+
+    #[test]
+    fn test_ctr() {
+        let ctr: _ = modes::Ctr::new(cipher(), [0xfe; 16]);
+        let mut exp: _ = message(1001);
+        ctr.apply_keystream(&mut exp);
+        for threads in THREADS.iter() {
+            let mut test: _ = message(1001);
+            Parallel::new(*threads).ctr_apply_keystream(&ctr, &mut test);
+            assert_eq!(exp, test);
+        }
+    }
+
+    #[test]
+    fn test_cbc_decrypt() {
+        let cbc: _ = modes::Cbc::new(cipher(), [0x11; 16]);
+        let mut ct: _ = message(16 * 45);
+        cbc.encrypt(&mut ct).unwrap();
+        for threads in THREADS.iter() {
+            let mut test: _ = ct.clone();
+            Parallel::new(*threads).cbc_decrypt(&cbc, &mut test).unwrap();
+            assert_eq!(message(16 * 45), test);
+        }
+    }
+
+    #[test]
+    fn test_xts() {
+        // eleven 96-byte data units from 5 onwards, the last only 40 bytes, checked with pyca.
+        let xts: _ = modes::Xts::new(
+            RjindaelCipher::new([
+                0xff, 0xfe, 0xfd, 0xfc, 0xfb, 0xfa, 0xf9, 0xf8,
+                0xf7, 0xf6, 0xf5, 0xf4, 0xf3, 0xf2, 0xf1, 0xf0
+            ]),
+            RjindaelCipher::new([
+                0xbf, 0xbe, 0xbd, 0xbc, 0xbb, 0xba, 0xb9, 0xb8,
+                0xb7, 0xb6, 0xb5, 0xb4, 0xb3, 0xb2, 0xb1, 0xb0
+            ])
+        );
+        let tail: _ = crate::test_util::unhex("6bcf502c35e402ba34aa8854dd44902f771c531ccb9a0c369af7aff86b1e078d5bd2eac45b073009");
+        for threads in THREADS.iter() {
+            let mut test: _ = message(1000);
+            Parallel::new(*threads).xts_encrypt(&xts, 5, 96, &mut test).unwrap();
+            assert_eq!(tail, test[960..].to_vec());
+            Parallel::new(*threads).xts_decrypt(&xts, 5, 96, &mut test).unwrap();
+            assert_eq!(message(1000), test);
+        }
+    }
+
+    #[test]
+    fn test_threads() {
+        assert_eq!(1, Parallel::new(0).threads());
+        assert!(Parallel::default().threads() >= 1);
+    }
+}
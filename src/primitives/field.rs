@@ -0,0 +1,263 @@
+//! GF(2^n) for any irreducible polynomial of degree n from two to eight and primitive
+//! generator, with the log and antilog tables generated at compile time when the field is a
+//! constant. The AES field is one instance; Reed-Solomon codes and secret sharing over
+//! GF(256) usually take 0x11d with generator 2, and 4-bit ciphers such as LED take 0x13.
+//! Table lookups are indexed by value, so secret data should go through the constant-time
+//! Byte arithmetic instead.
+
+use crate::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    poly: u16,
+    generator: u8,
+    // the number of nonzero elements, 2^n - 1.
+    order: usize,
+    // exp holds two periods so that a sum of two logs needs no reduction.
+    exp: [u8; 510],
+    log: [u8; 256],
+}
+
+impl Field {
+    // x^8 + x^4 + x^3 + x + 1 with generator x + 1.
+    pub const AES: Field = match Field::new(0x11b, 0x03) {
+        Ok(field) => field,
+        Err(_) => panic!("0x11b is irreducible and 0x03 generates its field."),
+    };
+
+    pub const fn new(poly: u16, generator: u8) -> Result<Self, Error> {
+        if !is_irreducible(poly) {
+            return Err(Error::InvalidParameter("the polynomial is not an irreducible polynomial of degree two to eight"))
+        }
+        let order: usize = (1 << degree(poly)) - 1;
+        if generator as usize > order {
+            return Err(Error::InvalidParameter("the generator is not an element of the field"))
+        }
+
+        let mut exp: [u8; 510] = [0; 510];
+        let mut log: [u8; 256] = [0; 256];
+        let mut alpha: u8 = 1;
+        let mut idx: usize = 0;
+        while idx < order {
+            // the powers of a primitive element only return to one after all of them.
+            if idx != 0 && alpha == 1 {
+                return Err(Error::InvalidParameter("the generator is not a primitive element"))
+            }
+            exp[idx] = alpha;
+            exp[idx + order] = alpha;
+            log[alpha as usize] = idx as u8;
+            alpha = mul_mod(alpha, generator, poly);
+            idx += 1;
+        }
+        if alpha != 1 {
+            return Err(Error::InvalidParameter("the generator is not a primitive element"))
+        }
+        Ok(Self { poly, generator, order, exp, log })
+    }
+
+    pub const fn poly(&self) -> u16 {
+        self.poly
+    }
+
+    // the n of GF(2^n), so elements are the values below 2^n.
+    pub const fn degree(&self) -> u32 {
+        degree(self.poly)
+    }
+
+    pub const fn generator(&self) -> u8 {
+        self.generator
+    }
+
+    // the generator raised to the power.
+    pub const fn exp(&self, power: u8) -> u8 {
+        self.exp[power as usize % self.order]
+    }
+
+    pub fn log(&self, val: u8) -> Result<u8, Error> {
+        self.check(val)?;
+        if val == 0 {
+            return Err(Error::InvalidParameter("zero has no logarithm"))
+        }
+        Ok(self.log[val as usize])
+    }
+
+    // both operands must be elements of the field. mul and pow stay infallible for the
+    // round functions, so only debug builds check.
+    pub const fn mul(&self, lhs: u8, rhs: u8) -> u8 {
+        debug_assert!(self.contains(lhs) && self.contains(rhs), "attempting to multiply a value that is not an element of the field.");
+        if lhs == 0 || rhs == 0 {
+            return 0
+        }
+        self.exp[self.log[lhs as usize] as usize + self.log[rhs as usize] as usize]
+    }
+
+    pub fn inverse(&self, val: u8) -> Result<u8, Error> {
+        self.check(val)?;
+        if val == 0 {
+            return Err(Error::InvalidParameter("zero has no inverse"))
+        }
+        Ok(self.exp[self.order - self.log[val as usize] as usize])
+    }
+
+    pub fn div(&self, lhs: u8, rhs: u8) -> Result<u8, Error> {
+        self.check(lhs)?;
+        Ok(self.mul(lhs, self.inverse(rhs)?))
+    }
+
+    pub const fn contains(&self, val: u8) -> bool {
+        val as usize <= self.order
+    }
+
+    fn check(&self, val: u8) -> Result<(), Error> {
+        match self.contains(val) {
+            true => Ok(()),
+            false => Err(Error::InvalidParameter("the value is not an element of the field")),
+        }
+    }
+
+    pub const fn pow(&self, val: u8, power: u32) -> u8 {
+        debug_assert!(self.contains(val), "attempting to raise a value that is not an element of the field.");
+        if power == 0 {
+            return 1
+        }
+        if val == 0 {
+            return 0
+        }
+        self.exp[((self.log[val as usize] as u64 * power as u64) % self.order as u64) as usize]
+    }
+}
+
+const fn degree(poly: u16) -> u32 {
+    15 - poly.leading_zeros()
+}
+
+// shift and add multiplication reduced by the polynomial.
+const fn mul_mod(lhs: u8, rhs: u8, poly: u16) -> u8 {
+    let mut acc: u16 = 0;
+    let mut shifted: u16 = lhs as u16;
+    let mut bit: u32 = 0;
+    while bit < 8 {
+        if (rhs >> bit) & 1 == 1 {
+            acc ^= shifted
+        }
+        shifted <<= 1;
+        if shifted >> degree(poly) != 0 {
+            shifted ^= poly
+        }
+        bit += 1;
+    }
+    acc as u8
+}
+
+// the remainder of carry-less division of lhs by rhs.
+const fn rem(lhs: u16, rhs: u16) -> u16 {
+    let rhs_deg: u32 = 15 - rhs.leading_zeros();
+    let mut acc: u16 = lhs;
+    while acc != 0 && 15 - acc.leading_zeros() >= rhs_deg {
+        acc ^= rhs << (15 - acc.leading_zeros() - rhs_deg)
+    }
+    acc
+}
+
+// a polynomial of degree n from two to eight is irreducible when nothing of degree one to
+// n / 2 divides it.
+pub const fn is_irreducible(poly: u16) -> bool {
+    if poly == 0 || degree(poly) < 2 || degree(poly) > 8 {
+        return false
+    }
+    let mut divisor: u16 = 0b10;
+    while divisor < 1 << (degree(poly) / 2 + 1) {
+        if rem(poly, divisor) == 0 {
+            return false
+        }
+        divisor += 1;
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::primitives::byte::Byte;
+
+    #[test]
+    fn test_irreducible() {
+        assert!(is_irreducible(0x11b));
+        assert!(is_irreducible(0x11d));
+        // x^8 + 1 = (x + 1)^8 and x^8 + x^4 + x^3 + x^2 = x^2 (x^6 + x^2 + x + 1).
+        assert!(!is_irreducible(0x101));
+        assert!(!is_irreducible(0x11c));
+        assert!(!is_irreducible(0x1b));
+        // there are 30 irreducible polynomials of degree eight.
+        assert_eq!(30, (0x100..0x200).filter(|poly| is_irreducible(*poly)).count());
+    }
+
+    #[test]
+    fn test_aes_field() {
+        let field: _ = Field::AES;
+        for lhs in 0..=255 {
+            for rhs in 0..=255 {
+                assert_eq!((Byte::new(lhs) * Byte::new(rhs)).as_inner(), field.mul(lhs, rhs));
+            }
+        }
+        assert_eq!(Ok(0xca), field.inverse(0x53));
+        assert_eq!(Ok(0x57), field.div(0xc1, 0x83));
+        assert!(field.div(0xc1, 0).is_err());
+        assert!(field.log(0).is_err());
+        assert_eq!(field.mul(0x57, field.mul(0x57, 0x57)), field.pow(0x57, 3));
+    }
+
+    #[test]
+    fn test_reed_solomon_field() {
+        // the QR code field, where the powers of 2 pass 0x80 then reduce to 0x1d.
+        const RS: Field = match Field::new(0x11d, 0x02) {
+            Ok(field) => field,
+            Err(_) => panic!("0x11d is primitive."),
+        };
+        assert_eq!(0x80, RS.exp(7));
+        assert_eq!(0x1d, RS.exp(8));
+        assert_eq!(Ok(8), RS.log(0x1d));
+        for val in 1..=255 {
+            assert_eq!(1, RS.mul(val, RS.inverse(val).unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_small_field() {
+        // GF(16) under x^4 + x + 1, where x^4 reduces to x + 1.
+        let field: _ = Field::new(0x13, 0x02).unwrap();
+        assert_eq!(4, field.degree());
+        assert_eq!(0x03, field.exp(4));
+        assert_eq!(1, field.exp(15));
+        // (x^3 + 1)x = x^4 + x = 1.
+        assert_eq!(0x01, field.mul(0x09, 0x02));
+        for val in 1..16 {
+            assert_eq!(1, field.mul(val, field.inverse(val).unwrap()));
+        }
+        assert!(field.contains(0x0f));
+        assert!(field.inverse(0x10).is_err());
+        assert!(field.log(0x10).is_err());
+        // there are three irreducible polynomials of degree four.
+        assert_eq!(vec![0x13, 0x19, 0x1f], (0x10..0x20).filter(|poly| is_irreducible(*poly)).collect::<Vec<u16>>());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not an element of the field")]
+    fn test_mul_outside_field() {
+        Field::new(0x13, 0x02).unwrap().mul(0x10, 0x02);
+    }
+
+    #[test]
+    fn test_invalid_fields() {
+        // x^4 + x^3 + x^2 + x + 1 is irreducible but x has order five in it.
+        assert!(Field::new(0x1f, 0x02).is_err());
+        assert!(Field::new(0x13, 0x10).is_err());
+        assert!(Field::new(0x03, 0x01).is_err());
+        // 2 only has order 51 in the AES field.
+        assert!(Field::new(0x11b, 0x02).is_err());
+        assert!(Field::new(0x11b, 0x00).is_err());
+        assert!(Field::new(0x11b, 0x01).is_err());
+        assert!(Field::new(0x101, 0x03).is_err());
+    }
+}
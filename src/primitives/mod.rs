@@ -1,4 +1,5 @@
 pub mod state;
 pub mod word;
 pub mod byte;
+pub mod field;
 pub mod sbox;
@@ -1,7 +1,7 @@
-use std::{iter,ops};
+use core::ops;
 use crate::primitives::{byte, word};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct State([word::Word; 4]);
 
 impl State {
@@ -14,18 +14,18 @@ impl State {
     }
 
     pub fn row(&self, idx: usize) -> word::Word {
-        self[idx].clone()
+        self[idx]
     }
 
     pub fn into_row_iter(self) -> impl Iterator<Item=byte::Byte> {
         self.transpose()
             .into_iter()
-            .flat_map(|x| x.clone().into_iter())
+            .flat_map(|x| x.into_iter())
     }
 
     pub fn into_col_iter(self) -> impl Iterator<Item=byte::Byte> {
         self.into_iter()
-            .flat_map(|x| x.clone().into_iter())
+            .flat_map(|x| x.into_iter())
     }
 
     pub fn into_rows(self) -> impl Iterator<Item=word::Word> {
@@ -71,12 +71,28 @@ impl State {
         self
     }
 
+    // multiplies every column by the polynomial modulo x^4 + 1.
+    pub fn mix_columns(&mut self, poly: &word::Word) -> &mut Self {
+        for j in 0..4 {
+            let col: _ = u32::from_le_bytes([
+                self[0][j].as_inner(),
+                self[1][j].as_inner(),
+                self[2][j].as_inner(),
+                self[3][j].as_inner(),
+            ]);
+            for (i, val) in poly.mul_column(col).to_le_bytes().iter().enumerate() {
+                self[i][j] = byte::Byte::new(*val)
+            }
+        }
+        self
+    }
+
     pub fn transpose(mut self) -> Self {
         for i in 0..4 {
             for j in i..4 {
                 if i != j { 
                     let mem_cpy_item: _ = self[i][j];
-                    self[i][j] = std::mem::replace(&mut self[j][i], mem_cpy_item)
+                    self[i][j] = core::mem::replace(&mut self[j][i], mem_cpy_item)
                 }
             }
         }
@@ -87,10 +103,7 @@ impl State {
 impl ops::BitXor<Self> for State {
     type Output = Self;
     fn bitxor(self, rhs: Self) -> Self::Output {
-        self.into_iter()
-            .zip(rhs)
-            .map(|(l,r)| l ^ r)
-            .collect()
+        Self::from(core::array::from_fn::<word::Word, 4, _>(|i| self[i] ^ rhs[i]))
     }
 }
 
@@ -98,12 +111,14 @@ impl ops::BitXor<Self> for State {
 impl ops::Index<usize> for State {
     type Output = word::Word;
     
+    #[inline]
     fn index(&self, idx: usize) -> &Self::Output {
         &self.0[idx]
     }
 }
 
 impl ops::IndexMut<usize> for State {
+    #[inline]
     fn index_mut(&mut self, idx: usize) -> &mut Self::Output {
         &mut self.0[idx]
     }
@@ -111,10 +126,10 @@ impl ops::IndexMut<usize> for State {
 
 impl IntoIterator for State {
     type Item = word::Word;
-    type IntoIter = std::vec::IntoIter<Self::Item>;
+    type IntoIter = core::array::IntoIter<Self::Item, 4>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.to_vec().into_iter()
+        IntoIterator::into_iter(self.0)
     }
 }
 
@@ -138,28 +153,19 @@ impl From<[u8;16]> for State {
     }
 }
 
-impl From<[word::Word; 4]> for State {
-    fn from(inner: [word::Word; 4]) -> Self {
-        State(inner)
+impl From<State> for [u8; 16] {
+    fn from(state: State) -> Self {
+        let mut buf: [u8; 16] = [0; 16];
+        for (byte, val) in buf.iter_mut().zip(state.into_col_iter()) {
+            *byte = val.as_inner()
+        }
+        buf
     }
 }
 
-impl iter::FromIterator<word::Word> for State {
-    fn from_iter<I: IntoIterator<Item=word::Word>>(object: I) -> Self {
-        let mut iter: _ = object.into_iter();
-        let arr: [word::Word; 4] = [
-            iter.next().expect("attempting to collect a Byte from a malformed iterator: required 4 additional elements."), 
-            iter.next().expect("attempting to collect a Byte from a malformed iterator: required 3 additional elements."), 
-            iter.next().expect("attempting to collect a Byte from a malformed iterator: required 2 additional elements."), 
-            iter.next().expect("attempting to collect a Byte from a malformed iterator: required 1 additional elements.")
-        ];
-        if iter.next().is_some() {
-            let (_, upper): _ = iter.size_hint();
-            let remaining_elements: _ = upper.unwrap();
-            panic!("attempting to collect a Byte from a malformed iterator: there are {} remaining elements.", remaining_elements+1)
-        } else {
-            Self::from(arr)
-        }
+impl From<[word::Word; 4]> for State {
+    fn from(inner: [word::Word; 4]) -> Self {
+        State(inner)
     }
 }
 
@@ -214,16 +220,23 @@ mod test {
         println!("{:?}\n{:?}", test, exp);
 
         for (exp_word, test_word) in exp.into_iter()
-            .zip(test.into_iter())
+            .zip(test)
          {
             for (exp_byte, test_byte) in exp_word.into_iter()
-                .zip(test_word.into_iter())
+                .zip(test_word)
             {
                 assert_eq!(exp_byte, test_byte)
             }
         }
     }
 
+    #[test]
+    fn test_into_bytes() {
+        let test: [u8; 16] = State::test_vector().into();
+        let exp: [u8; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+        assert_eq!(exp, test)
+    }
+
     #[test]
     fn test_index() {
         let state: _ = State::test_vector();
@@ -264,6 +277,25 @@ mod test {
         assert_eq!(state.row(3), exp)
     }
 
+    #[test]
+    fn test_mix_columns() {
+        // the polynomial and the circulant matrix of its coefficients agree.
+        let matrix: _ = State::from([
+            0x02, 0x03, 0x01, 0x01,
+            0x01, 0x02, 0x03, 0x01,
+            0x01, 0x01, 0x02, 0x03,
+            0x03, 0x01, 0x01, 0x02
+        ]);
+        let mut exp: _ = State::test_vector();
+        exp.mix(matrix);
+        let mut test: _ = State::test_vector();
+        test.mix_columns(&word::Word::MIX);
+        assert_eq!(exp, test);
+
+        test.mix_columns(&word::Word::INV_MIX);
+        assert_eq!(State::test_vector(), test);
+    }
+
     #[test]
     fn test_into_cols() {
         let state: _ = State::test_vector();
@@ -1,18 +1,19 @@
-use std::{slice, ops,iter};
+use core::{slice, ops};
 
-use crate::primitives::{sbox, byte};
+use crate::{primitives::byte, Error};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Word([byte::Byte; 4]);
 
 impl Word {
+    #[inline]
     pub fn iter_mut(&mut self) -> slice::IterMut<'_,byte::Byte> {
         self.0.iter_mut()
     }
 }
 
 impl Word {
-    pub fn rotate(mut self) -> Self {
+    pub fn rotate(self) -> Self {
         self << 1
     }
 
@@ -31,6 +32,97 @@ impl Word {
     } 
 }
 
+// a word is also the polynomial w[0] + w[1]x + w[2]x^2 + w[3]x^3 over GF(2^8), multiplied
+// modulo x^4 + 1 as in FIPS-197 section 4.3.
+impl Word {
+    // a(x) = {03}x^3 + {01}x^2 + {01}x + {02}, the MixColumns polynomial.
+    pub const MIX: Word = Word([byte::Byte::new(0x02), byte::Byte::new(0x01), byte::Byte::new(0x01), byte::Byte::new(0x03)]);
+
+    // a^-1(x) = {0b}x^3 + {0d}x^2 + {09}x + {0e}, the InvMixColumns polynomial.
+    pub const INV_MIX: Word = Word([byte::Byte::new(0x0e), byte::Byte::new(0x09), byte::Byte::new(0x0d), byte::Byte::new(0x0b)]);
+
+    const ONE: Word = Word([byte::Byte::new(0x01), byte::Byte::new(0x00), byte::Byte::new(0x00), byte::Byte::new(0x00)]);
+
+    // x^4 + 1 = (x + 1)^4, so w is a unit exactly when w(1) is nonzero. Then w = c(1 + m)
+    // with c = w(1) and m a multiple of x + 1, so m^4 = 0 and the inverse is
+    // c^-1 (1 + m + m^2 + m^3).
+    pub fn inverse(&self) -> Result<Word, Error> {
+        let c: _ = self.0.iter().fold(byte::Byte::new(0), |acc, val| acc + *val);
+        if c == byte::Byte::new(0) {
+            return Err(Error::InvalidParameter("the polynomial is not invertible modulo x^4 + 1"))
+        }
+        let c_inv: _ = c.inverse();
+        let m: _ = (*self).scale(c_inv) ^ Self::ONE;
+        let m2: _ = m * m;
+        let m3: _ = m2 * m;
+        Ok((Self::ONE ^ m ^ m2 ^ m3).scale(c_inv))
+    }
+
+    // a column packed into a u32 with the coefficient of x^i in byte i, times this
+    // polynomial. Multiplying by x^i is then a rotation and xtime runs on all four bytes at
+    // once. The MixColumns polynomial and its inverse take unrolled paths, the inverse
+    // through a^-1(x) = a(x)({04}x^2 + {05}).
+    #[inline]
+    pub fn mul_column(&self, col: u32) -> u32 {
+        if *self == Self::MIX {
+            mix_packed(col)
+        } else if *self == Self::INV_MIX {
+            mix_packed(col ^ xtime_packed(xtime_packed(col ^ col.rotate_left(16))))
+        } else {
+            mul_packed(self, col)
+        }
+    }
+
+    fn scale(mut self, rhs: byte::Byte) -> Self {
+        for val in self.iter_mut() {
+            *val = *val * rhs
+        }
+        self
+    }
+}
+
+// xtime on each byte of the word.
+#[inline]
+const fn xtime_packed(word: u32) -> u32 {
+    ((word & 0x7f7f_7f7f) << 1) ^ (((word >> 7) & 0x0101_0101) * 0x1b)
+}
+
+// {02}c + {01}xc + {01}x^2c + {03}x^3c.
+#[inline]
+pub(crate) const fn mix_packed(col: u32) -> u32 {
+    let rot: _ = col.rotate_left(24);
+    xtime_packed(col ^ rot) ^ col.rotate_left(8) ^ col.rotate_left(16) ^ rot
+}
+
+// any polynomial, whose public coefficient bits may steer the loop.
+fn mul_packed(poly: &Word, col: u32) -> u32 {
+    let mut acc: u32 = 0;
+    for (i, coeff) in poly.into_iter().enumerate() {
+        let mut power: _ = col.rotate_left(8 * i as u32);
+        let mut bits: _ = coeff.as_inner();
+        while bits != 0 {
+            acc ^= power & 0u32.wrapping_sub((bits & 1) as u32);
+            power = xtime_packed(power);
+            bits >>= 1;
+        }
+    }
+    acc
+}
+
+impl ops::Mul<Self> for Word {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut buf: Word = Word::default();
+        for i in 0..4 {
+            for j in 0..4 {
+                buf[(i + j) % 4] = buf[(i + j) % 4] + self[i] * rhs[j]
+            }
+        }
+        buf
+    }
+}
+
 impl ops::Shl<usize> for Word {
     type Output = Self;
 
@@ -64,21 +156,20 @@ impl ops::Shr<usize> for Word {
 impl ops::BitXor<Self> for Word {
     type Output = Self;
     fn bitxor(self, rhs: Self) -> Self::Output {
-        self.into_iter()
-            .zip(rhs)
-            .map(|(lhs,rhs)| lhs ^ rhs)
-            .collect()
+        Self::from(core::array::from_fn::<byte::Byte, 4, _>(|i| self[i] ^ rhs[i]))
     }
 }
 
 impl ops::Index<usize> for Word {
     type Output = byte::Byte;
+    #[inline]
     fn index(&self, idx: usize) -> &Self::Output {
         &self.0[idx]
     }
 }
 
 impl ops::IndexMut<usize> for Word {
+    #[inline]
     fn index_mut(&mut self, idx: usize) -> &mut Self::Output {
         &mut self.0[idx]
     }
@@ -86,29 +177,10 @@ impl ops::IndexMut<usize> for Word {
 
 impl IntoIterator for Word {
     type Item = byte::Byte;
-    type IntoIter = std::vec::IntoIter<Self::Item>;
+    type IntoIter = core::array::IntoIter<Self::Item, 4>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.to_vec().into_iter()
-    }
-}
-
-impl iter::FromIterator<byte::Byte> for Word {
-    fn from_iter<I: IntoIterator<Item=byte::Byte>>(object: I) -> Self {
-        let mut iter: _ = object.into_iter();
-        let arr: [byte::Byte; 4] = [
-            iter.next().expect("attempting to collect a Byte from a malformed iterator: required 4 additional elements."), 
-            iter.next().expect("attempting to collect a Byte from a malformed iterator: required 3 additional elements."), 
-            iter.next().expect("attempting to collect a Byte from a malformed iterator: required 2 additional elements."), 
-            iter.next().expect("attempting to collect a Byte from a malformed iterator: required 1 additional elements.")
-        ];
-        if iter.next().is_some() {
-            let (_, upper): _ = iter.size_hint();
-            let remaining_elements: _ = upper.unwrap();
-            panic!("attempting to collect a Byte from a malformed iterator: there are {} remaining elements.", remaining_elements+1)
-        } else {
-            Self::from(arr)
-        }
+        IntoIterator::into_iter(self.0)
     }
 }
 
@@ -149,6 +221,46 @@ mod test {
         assert_eq!(test, exp)        
     }
 
+    #[test]
+    fn test_mul() {
+        // FIPS-197 section 4.3, x^i modulo x^4 + 1 is x^(i mod 4), so x times a word rotates it.
+        let x: _ = Word::from([0, 1, 0, 0]);
+        let test: _ = Word::from([0xaf, 0x7f, 0x67, 0x98]);
+        assert_eq!(test >> 1, x * test);
+        assert_eq!(test.clone(), Word::ONE * test);
+
+        // the first column of the FIPS-197 appendix B MixColumns.
+        let test: _ = Word::MIX * Word::from([0xd4, 0xbf, 0x5d, 0x30]);
+        assert_eq!(Word::from([0x04, 0x66, 0x81, 0xe5]), test);
+    }
+
+    #[test]
+    fn test_inverse() {
+        assert_eq!(Ok(Word::INV_MIX), Word::MIX.inverse());
+        assert_eq!(Ok(Word::MIX), Word::INV_MIX.inverse());
+        assert_eq!(Word::ONE, Word::MIX * Word::INV_MIX);
+
+        let test: _ = Word::from([0x13, 0x57, 0x9b, 0x2e]);
+        assert_eq!(Word::ONE, test * test.inverse().unwrap());
+
+        // the coefficients sum to zero, so x + 1 divides the word.
+        assert!(Word::from([0x01, 0x01, 0x00, 0x00]).inverse().is_err());
+        assert!(Word::from([0x12, 0x34, 0x56, 0x70]).inverse().is_err());
+    }
+
+    #[test]
+    fn test_mul_column() {
+        // the packed multiply, unrolled or not, agrees with the polynomial one.
+        let pack: _ = |word: Word| u32::from_le_bytes([word[0].as_inner(), word[1].as_inner(), word[2].as_inner(), word[3].as_inner()]);
+        for col in [[0xdb, 0x13, 0x53, 0x45], [0xf2, 0x0a, 0x22, 0x5c], [0xff, 0x80, 0x01, 0x00]].iter() {
+            let col: _ = Word::from(*col);
+            for poly in [Word::MIX, Word::INV_MIX, Word::from([0x13, 0x57, 0x9b, 0x2e])].iter() {
+                assert_eq!(pack(*poly * col), poly.mul_column(pack(col)));
+                assert_eq!(pack(*poly * col), mul_packed(poly, pack(col)));
+            }
+        }
+    }
+
     #[test]
     fn test_bitxor() {
         let word_a: _ = Word::from([0,1,2,3]);
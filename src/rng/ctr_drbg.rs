@@ -0,0 +1,435 @@
+//! CTR_DRBG from SP 800-90A Rev. 1 over AES-128, with a 128-bit counter.
+//! The derivation function condenses arbitrary entropy, nonce and additional input into
+//! seed material; without it the entropy must be full entropy of exactly the seed length.
+
+use crate::{mac, rng, Block, BLOCK_SIZE, Error, RjindaelCipher};
+
+const KEY_LEN: usize = BLOCK_SIZE;
+pub(super) const SEED_LEN: usize = KEY_LEN + BLOCK_SIZE;
+const SECURITY_STRENGTH: usize = 16;
+
+// the SP 800-90A table 3 limits for AES-128.
+const MAX_RESEED_INTERVAL: u64 = 1 << 48;
+const MAX_REQUEST: usize = 1 << 16;
+const MAX_INPUT: usize = u32::MAX as usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Derivation {
+    BlockCipherDf,
+    None,
+}
+
+pub struct CtrDrbg<E> {
+    source: E,
+    derivation: Derivation,
+    key: Block,
+    v: Block,
+    reseed_counter: u64,
+    reseed_interval: u64,
+    prediction_resistance: bool,
+}
+
+impl<E: rng::EntropySource> CtrDrbg<E> {
+    // instantiates from the entropy source, taking the nonce from the source as well.
+    pub fn new(source: E, derivation: Derivation, personalization: &[u8]) -> Result<Self, Error> {
+        let mut drbg: _ = Self::uninstantiated(source, derivation);
+        // only the derivation function takes a nonce, so the source is not asked for one otherwise.
+        let mut nonce: Vec<u8> = Vec::new();
+        if derivation == Derivation::BlockCipherDf {
+            nonce.resize(SECURITY_STRENGTH / 2, 0);
+            drbg.source.fill_entropy(&mut nonce)?;
+        }
+        drbg.instantiate(&nonce, personalization)?;
+        Ok(drbg)
+    }
+
+    // instantiates with the derivation function and a caller supplied nonce.
+    pub fn with_nonce(source: E, nonce: &[u8], personalization: &[u8]) -> Result<Self, Error> {
+        let mut drbg: _ = Self::uninstantiated(source, Derivation::BlockCipherDf);
+        drbg.instantiate(nonce, personalization)?;
+        Ok(drbg)
+    }
+
+    // reseeds from the entropy source before every request.
+    pub fn prediction_resistance(mut self, enabled: bool) -> Self {
+        self.prediction_resistance = enabled;
+        self
+    }
+
+    // the number of requests between automatic reseeds, at most 2^48.
+    pub fn reseed_interval(mut self, interval: u64) -> Self {
+        self.reseed_interval = interval.clamp(1, MAX_RESEED_INTERVAL);
+        self
+    }
+
+    pub fn reseed(&mut self, additional: &[u8]) -> Result<(), Error> {
+        check_input(self.derivation, additional)?;
+        let mut entropy: _ = vec![0; self.entropy_len()];
+        self.source.fill_entropy(&mut entropy)?;
+
+        let seed: _ = self.seed_material(&[&entropy, additional]);
+        self.update(&seed);
+        self.reseed_counter = 1;
+        Ok(())
+    }
+
+    pub fn generate(&mut self, out: &mut [u8], additional: &[u8]) -> Result<(), Error> {
+        if out.len() > MAX_REQUEST {
+            return Err(Error::OutputTooLong { len: out.len(), max: MAX_REQUEST })
+        }
+        check_input(self.derivation, additional)?;
+
+        let mut additional: _ = additional;
+        if self.prediction_resistance || self.reseed_counter > self.reseed_interval {
+            self.reseed(additional)?;
+            additional = &[];
+        }
+
+        let has_additional: _ = !additional.is_empty();
+        let additional: [u8; SEED_LEN] = match has_additional {
+            true => self.seed_material(&[additional]),
+            false => [0; SEED_LEN]
+        };
+        if has_additional {
+            self.update(&additional);
+        }
+
+        let cipher: _ = RjindaelCipher::new(self.key);
+        for chunk in out.chunks_mut(BLOCK_SIZE) {
+            increment(&mut self.v);
+            let mut block: Block = self.v;
+            cipher.encrypt_block(&mut block);
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+
+        self.update(&additional);
+        self.reseed_counter += 1;
+        Ok(())
+    }
+
+    fn uninstantiated(source: E, derivation: Derivation) -> Self {
+        Self {
+            source,
+            derivation,
+            key: [0; KEY_LEN],
+            v: [0; BLOCK_SIZE],
+            reseed_counter: 0,
+            reseed_interval: MAX_RESEED_INTERVAL,
+            prediction_resistance: false
+        }
+    }
+
+    fn instantiate(&mut self, nonce: &[u8], personalization: &[u8]) -> Result<(), Error> {
+        check_input(self.derivation, personalization)?;
+        let mut entropy: _ = vec![0; self.entropy_len()];
+        self.source.fill_entropy(&mut entropy)?;
+
+        let seed: _ = self.seed_material(&[&entropy, nonce, personalization]);
+        self.update(&seed);
+        self.reseed_counter = 1;
+        Ok(())
+    }
+
+    fn entropy_len(&self) -> usize {
+        match self.derivation {
+            Derivation::BlockCipherDf => SECURITY_STRENGTH,
+            Derivation::None => SEED_LEN
+        }
+    }
+
+    // with the derivation function every input is condensed, without it the zero padded
+    // personalization or additional input is XORed onto the full entropy input.
+    fn seed_material(&self, inputs: &[&[u8]]) -> [u8; SEED_LEN] {
+        match self.derivation {
+            Derivation::BlockCipherDf => block_cipher_df(&inputs.concat()),
+            Derivation::None => {
+                let mut buf: [u8; SEED_LEN] = [0; SEED_LEN];
+                for input in inputs.iter() {
+                    for (val, byte) in buf.iter_mut().zip(input.iter()) {
+                        *val ^= byte
+                    }
+                }
+                buf
+            }
+        }
+    }
+
+    fn update(&mut self, provided: &[u8; SEED_LEN]) {
+        let cipher: _ = RjindaelCipher::new(self.key);
+        let mut temp: [u8; SEED_LEN] = [0; SEED_LEN];
+        for chunk in temp.chunks_mut(BLOCK_SIZE) {
+            increment(&mut self.v);
+            let mut block: Block = self.v;
+            cipher.encrypt_block(&mut block);
+            chunk.copy_from_slice(&block);
+        }
+        for (val, byte) in temp.iter_mut().zip(provided.iter()) {
+            *val ^= byte
+        }
+
+        self.key.copy_from_slice(&temp[..KEY_LEN]);
+        self.v.copy_from_slice(&temp[KEY_LEN..]);
+    }
+}
+
+impl<E: rng::EntropySource> rng::Rng for CtrDrbg<E> {
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        for chunk in dest.chunks_mut(MAX_REQUEST) {
+            self.generate(chunk, &[])?
+        }
+        Ok(())
+    }
+}
+
+fn check_input(derivation: Derivation, input: &[u8]) -> Result<(), Error> {
+    let max: _ = match derivation {
+        Derivation::BlockCipherDf => MAX_INPUT,
+        Derivation::None => SEED_LEN
+    };
+    match input.len() > max {
+        true => Err(Error::InputTooLong { len: input.len(), max }),
+        false => Ok(())
+    }
+}
+
+fn increment(v: &mut Block) {
+    *v = u128::from_be_bytes(*v).wrapping_add(1).to_be_bytes()
+}
+
+// Block_Cipher_df from SP 800-90A section 10.3.2, returning seedlen bits.
+pub(super) fn block_cipher_df(input: &[u8]) -> [u8; SEED_LEN] {
+    let mut s: Vec<u8> = Vec::with_capacity(BLOCK_SIZE + 9 + input.len());
+    s.extend_from_slice(&[0; BLOCK_SIZE]);
+    s.extend_from_slice(&(input.len() as u32).to_be_bytes());
+    s.extend_from_slice(&(SEED_LEN as u32).to_be_bytes());
+    s.extend_from_slice(input);
+    s.push(0x80);
+    s.resize(s.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, 0);
+
+    // BCC is CBC-MAC with a zero IV over IV || S, where the IV block carries the counter.
+    let df_key: Block = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+        0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f
+    ];
+    let bcc: _ = RjindaelCipher::new(df_key);
+    let mut temp: [u8; SEED_LEN] = [0; SEED_LEN];
+    for (idx, chunk) in temp.chunks_mut(BLOCK_SIZE).enumerate() {
+        s[..4].copy_from_slice(&(idx as u32).to_be_bytes());
+        chunk.copy_from_slice(&mac::cbc::chain(&bcc, [0; BLOCK_SIZE], &s));
+    }
+
+    let mut key: Block = [0; KEY_LEN];
+    key.copy_from_slice(&temp[..KEY_LEN]);
+    let mut x: Block = [0; BLOCK_SIZE];
+    x.copy_from_slice(&temp[KEY_LEN..]);
+
+    let cipher: _ = RjindaelCipher::new(key);
+    let mut buf: [u8; SEED_LEN] = [0; SEED_LEN];
+    for chunk in buf.chunks_mut(BLOCK_SIZE) {
+        cipher.encrypt_block(&mut x);
+        chunk.copy_from_slice(&x);
+    }
+    buf
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Rng;
+    use crate::test_util::unhex;
+
+    // hands out the entropy input of a test vector in order, like the CAVP harness.
+    fn replay(entropy: &str) -> impl FnMut(&mut [u8]) -> Result<(), Error> {
+        let entropy: _ = unhex(entropy);
+        let mut offset: usize = 0;
+        move |buf: &mut [u8]| {
+            let end: _ = offset + buf.len();
+            if end > entropy.len() {
+                return Err(Error::EntropyFailure)
+            }
+            buf.copy_from_slice(&entropy[offset..end]);
+            offset = end;
+            Ok(())
+        }
+    }
+
+    // CAVS 14.3 CTR_DRBG AES-128 use df vectors, as carried by the mbed TLS test suite.
+    // each case instantiates, generates twice and checks the second 512-bit output.
+    #[test]
+    fn test_cavs_no_reseed() {
+        let cases: [(&str, &str, &str, &str, &str); 4] = [
+            ("aad471ef3ef1d203", "890eb067acf7382eff80b0c73bc872c6", "", "",
+             "a5514ed7095f64f3d0d3a5760394ab42062f373a25072a6ea6bcfd8489e94af6cf18659fea22ed1ca0a9e33f718b115ee536b12809c31b72b08ddd8be1910fa3"),
+            ("259195269ec11af6", "2d2ab564202918c4ef5b102dda385a18", "", "",
+             "2c5cd79ed87622a91b8654c8903d852242cd49cb5df2d4b4150584301c59f01fd95a702ac157c84cc15f42c8211335672d8ce1291ef9b1def78149a04fa2697c"),
+            ("026c768fd577b92a", "b408cefb5bc7157d3f26cb95a8b1d7ac", "5737ef81dee365b6dadb3feebf5d1084", "3368a516b3431a3daaa60dc8743c8297",
+             "4e909ebb24147a0004063a5e47ee044fead610d62324bd0f963f756fb91361e8b87e3a76a398143fe88130fe1b547b661a6480c711b739f18a9df3ae51d41bc9"),
+            ("7ee2614ead3c128e", "8b80936e69c67edb771c28f9b9452124", "fc35cba97a1e211bc420e8af53f8e13c", "fba438aaa75a3cd4cd0cce399bfec74a",
+             "6721cc1ada5ebc1713f74c759000765652eeb5f3f9c24fb9341b36a369cec1d27ea80d6b73b56047af07138c5a43c99a87753115c471b8587ea65fa2065e3ce0"),
+        ];
+
+        for (nonce, entropy, add1, add2, exp) in cases.iter() {
+            let mut drbg: _ = CtrDrbg::with_nonce(replay(entropy), &unhex(nonce), &[]).unwrap();
+            let mut out: _ = [0; 64];
+            drbg.generate(&mut out, &unhex(add1)).unwrap();
+            drbg.generate(&mut out, &unhex(add2)).unwrap();
+            assert_eq!(unhex(exp), out.to_vec());
+        }
+    }
+
+    #[test]
+    fn test_cavs_reseed() {
+        let entropy: _ = "0f65da13dca407999d4773c2b4a11d851dea0a12c52bf64339dd291c80d8ca89";
+        let mut drbg: _ = CtrDrbg::with_nonce(replay(entropy), &unhex("5209e5b4ed82a234"), &[]).unwrap();
+        drbg.reseed(&[]).unwrap();
+
+        let mut out: _ = [0; 64];
+        drbg.generate(&mut out, &[]).unwrap();
+        drbg.generate(&mut out, &[]).unwrap();
+        let exp: _ = unhex("2859cc468a76b08661ffd23b28547ffd0997ad526a0f51261b99ed3a37bd407bf418dbe6c6c3e26ed0ddefcb7474d899bd99f3655427519fc5b4057bcaf306d4");
+        assert_eq!(exp, out.to_vec());
+    }
+
+    #[test]
+    fn test_cavs_prediction_resistance() {
+        let entropy: _ = "5d4041942bcf68864a4997d8171f1f9fef55a769b7eaf03fe082029bb32a2b9d8239e865c0a42e14b964b9c09de85a20";
+        let mut drbg: _ = CtrDrbg::with_nonce(replay(entropy), &unhex("d4f1f4ae08bcb3e1"), &[])
+            .unwrap()
+            .prediction_resistance(true);
+
+        let mut out: _ = [0; 64];
+        drbg.generate(&mut out, &[]).unwrap();
+        drbg.generate(&mut out, &[]).unwrap();
+        let exp: _ = unhex("4155320287eedcf7d484c2c2a1e2eb64b9c9ce77c87202a1ae1616c7a5cfd1c687c7a0bfcc85bda48fdd4629fd330c22d0a76076f88fc7cd04037ee06b7af602");
+        assert_eq!(exp, out.to_vec());
+
+        // every request consumed fresh entropy, so the exhausted source now fails.
+        assert_eq!(Err(Error::EntropyFailure), drbg.generate(&mut out, &[]));
+    }
+
+    // CTR_DRBG.rsp [AES-128 no df], COUNT = 0 of the no_reseed and pr_false files, with no
+    // personalization or additional input and a 512-bit ReturnedBits from the second generate.
+    #[test]
+    fn test_cavp_no_derivation() {
+        let entropy: _ = "ce50f33da5d4c1d3d4004eb35244b7f2cd7f2e5076fbf6780a7ff634b249a5fc";
+        let mut drbg: _ = CtrDrbg::new(replay(entropy), Derivation::None, &[]).unwrap();
+        let mut out: _ = [0; 64];
+        drbg.generate(&mut out, &[]).unwrap();
+        drbg.generate(&mut out, &[]).unwrap();
+        let exp: _ = unhex("6545c0529d372443b392ceb3ae3a99a30f963eaf313280f1d1a1e87f9db373d361e75d18018266499cccd64d9bbb8de0185f213383080faddec46bae1f784e5a");
+        assert_eq!(exp, out.to_vec());
+
+        // EntropyInput then EntropyInputReseed.
+        let entropy: _ = concat!(
+            "ed1e7f21ef66ea5d8e2a85b9337245445b71d6393a4eecb0e63c193d0f72f9a9",
+            "303fb519f0a4e17d6df0b6426aa0ecb2a36079bd48be47ad2a8dbfe48da3efad"
+        );
+        let exp: _ = unhex("f80111d08e874672f32f42997133a5210f7a9375e22cea70587f9cfafebe0f6a6aa2eb68e7dd9164536d53fa020fcab20f54caddfab7d6d91e5ffec1dfd8deaa");
+        let mut drbg: _ = CtrDrbg::new(replay(entropy), Derivation::None, &[]).unwrap();
+        drbg.reseed(&[]).unwrap();
+        drbg.generate(&mut out, &[]).unwrap();
+        drbg.generate(&mut out, &[]).unwrap();
+        assert_eq!(exp, out.to_vec());
+
+        // the reseed prediction resistance makes before a request is the explicit one above,
+        // so the same vector checks it when resistance is only on for the first request.
+        let mut drbg: _ = CtrDrbg::new(replay(entropy), Derivation::None, &[]).unwrap().prediction_resistance(true);
+        drbg.generate(&mut out, &[]).unwrap();
+        let mut drbg: _ = drbg.prediction_resistance(false);
+        drbg.generate(&mut out, &[]).unwrap();
+        assert_eq!(exp, out.to_vec());
+    }
+
+    // the pr_true sequence without df: EntropyInput, then EntropyInputPR before each of the
+    // two generates. The pr_true CAVP file could not be obtained, so the output comes from
+    // OpenSSL 3.5's CTR-DRBG fed the same entropy through its TEST-RAND source. Fed the
+    // entropy of the two vectors above, it returns their ReturnedBits.
+    #[test]
+    fn test_prediction_resistance_no_derivation() {
+        let entropy: _ = concat!(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f",
+            "404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f"
+        );
+        let mut drbg: _ = CtrDrbg::new(replay(entropy), Derivation::None, &[]).unwrap().prediction_resistance(true);
+        let mut out: _ = [0; 64];
+        drbg.generate(&mut out, &[]).unwrap();
+        drbg.generate(&mut out, &[]).unwrap();
+        let exp: _ = unhex("424f6e1a9647c1288cb1c1f1b6c348983b09193f8c4756d7fbbb32b8667c50d8291c09ea4bc3555ab57bdc1db8bce1b4ccef41363694ed38b2dbaf1f11f55bc2");
+        assert_eq!(exp, out.to_vec());
+
+        // without df the source is asked for the entropy input alone, not an empty nonce.
+        let mut calls: usize = 0;
+        let source: _ = |buf: &mut [u8]| {
+            calls += 1;
+            buf.iter_mut().for_each(|x| *x = 0);
+            Ok(())
+        };
+        CtrDrbg::new(source, Derivation::None, &[]).unwrap();
+        assert_eq!(1, calls);
+    }
+
+    // personalization and additional input without df, cross-checked against an independent model.
+    #[test]
+    fn test_no_derivation() {
+        let entropy: Vec<u8> = (0..32).collect();
+        let source: _ = move |buf: &mut [u8]| {
+            buf.copy_from_slice(&entropy[..buf.len()]);
+            Ok(())
+        };
+        let mut drbg: _ = CtrDrbg::new(source, Derivation::None, &unhex("000102030405060708090a0b0c0d0e0f")).unwrap();
+
+        let mut out: _ = [0; 64];
+        drbg.generate(&mut out, &[]).unwrap();
+        drbg.generate(&mut out, &[0xa5; 32]).unwrap();
+        let exp: _ = unhex("a51ca389116a62fe160ff2a9be6397e4d25eb6c52401b51b1dead1bc68f1fde82dee17af096b82b461d9f96cc4920df86ecdecfdab43a9538dd4a76b2544eb35");
+        assert_eq!(exp, out.to_vec());
+
+        // additional input longer than the seed length needs the derivation function.
+        assert!(drbg.generate(&mut out, &[0; 33]).is_err());
+    }
+
+    #[test]
+    fn test_reseed_interval() {
+        let mut calls: usize = 0;
+        let source: _ = |buf: &mut [u8]| {
+            calls += 1;
+            buf.iter_mut().for_each(|x| *x = calls as u8);
+            Ok(())
+        };
+        {
+            let mut drbg: _ = CtrDrbg::new(source, Derivation::BlockCipherDf, b"interval")
+                .unwrap()
+                .reseed_interval(2);
+
+            let mut out: _ = [0; 16];
+            for _ in 0..5 {
+                drbg.generate(&mut out, &[]).unwrap();
+            }
+        }
+        // entropy and nonce, then a reseed after every second request.
+        assert_eq!(4, calls);
+    }
+
+    #[test]
+    fn test_request_limit() {
+        let mut drbg: _ = CtrDrbg::new(rng::OsEntropy, Derivation::BlockCipherDf, &[]).unwrap();
+        let mut out: _ = vec![0; MAX_REQUEST + 1];
+        assert_eq!(
+            Err(Error::OutputTooLong { len: MAX_REQUEST + 1, max: MAX_REQUEST }),
+            drbg.generate(&mut out, &[])
+        );
+        assert!(drbg.generate(&mut out[..MAX_REQUEST], &[]).is_ok());
+    }
+
+    #[test]
+    fn test_rng() {
+        let mut drbg: _ = CtrDrbg::new(rng::OsEntropy, Derivation::BlockCipherDf, &[]).unwrap();
+        let (a, b): _ = (drbg.gen_block(), drbg.gen_block());
+        assert_ne!(a, b);
+
+        let mut buf: _ = [0; 40];
+        drbg.fill_bytes(&mut buf);
+        assert_ne!([0; 40], buf);
+    }
+}
@@ -0,0 +1,42 @@
+use std::{fs, io::Read};
+
+use crate::Error;
+
+pub trait EntropySource {
+    fn fill_entropy(&mut self, buf: &mut [u8]) -> Result<(), Error>;
+}
+
+// reads entropy from the operating system through /dev/urandom.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OsEntropy;
+
+impl EntropySource for OsEntropy {
+    fn fill_entropy(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        fs::File::open("/dev/urandom")
+            .and_then(|mut file| file.read_exact(buf))
+            .map_err(|_| Error::EntropyFailure)
+    }
+}
+
+impl<F> EntropySource for F
+where
+    F: FnMut(&mut [u8]) -> Result<(), Error>
+{
+    fn fill_entropy(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        self(buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_os_entropy() {
+        let mut first: _ = [0; 32];
+        let mut second: _ = [0; 32];
+        OsEntropy.fill_entropy(&mut first).unwrap();
+        OsEntropy.fill_entropy(&mut second).unwrap();
+        assert_ne!(first, second)
+    }
+}
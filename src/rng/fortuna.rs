@@ -0,0 +1,404 @@
+//! Fortuna from Ferguson, Schneier and Kohno, adapted to the primitives in this crate.
+//! Events are spread over 32 pools that are hashed with Miyaguchi-Preneel rather than
+//! SHA-256, and the generator runs AES-128 in counter mode, rekeying after every request.
+//! The entropy source is polled into the pools whenever the reseed delay has passed, so
+//! random_data keeps collecting fresh entropy without the caller calling poll.
+
+use std::{ffi, fs, path, time};
+use std::io::Write;
+
+use crate::{hash, rng, Block, BLOCK_SIZE, Error, RjindaelCipher};
+use crate::rng::ctr_drbg::{block_cipher_df, SEED_LEN};
+
+const POOL_COUNT: usize = 32;
+const MIN_POOL_SIZE: usize = 64;
+const MAX_EVENT: usize = 32;
+const MAX_REQUEST: usize = 1 << 20;
+const SEED_FILE_LEN: usize = 64;
+const RESEED_DELAY: time::Duration = time::Duration::from_millis(100);
+const POOL_HASH: hash::Compression = hash::Compression::MiyaguchiPreneel;
+
+// the source id used for bytes polled from the entropy source.
+pub const POLL_SOURCE_ID: u8 = 0;
+
+#[derive(Debug, Clone, Default)]
+struct Generator {
+    key: Block,
+    counter: u128,
+}
+
+impl Generator {
+    fn is_seeded(&self) -> bool {
+        self.counter != 0
+    }
+
+    fn reseed(&mut self, seed: &[u8]) {
+        let derived: [u8; SEED_LEN] = block_cipher_df(&[&self.key[..], seed].concat());
+        self.key.copy_from_slice(&derived[..BLOCK_SIZE]);
+        self.counter = self.counter.wrapping_add(1);
+    }
+
+    fn next_block(&mut self, cipher: &RjindaelCipher) -> Block {
+        let mut block: Block = self.counter.to_be_bytes();
+        cipher.encrypt_block(&mut block);
+        self.counter = self.counter.wrapping_add(1);
+        block
+    }
+
+    fn pseudo_random_data(&mut self, out: &mut [u8]) -> Result<(), Error> {
+        if !self.is_seeded() {
+            return Err(Error::EntropyFailure)
+        }
+        if out.len() > MAX_REQUEST {
+            return Err(Error::OutputTooLong { len: out.len(), max: MAX_REQUEST })
+        }
+
+        let cipher: _ = RjindaelCipher::new(self.key);
+        for chunk in out.chunks_mut(BLOCK_SIZE) {
+            let block: _ = self.next_block(&cipher);
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+        // rekey so that a later compromise cannot recover this output.
+        self.key = self.next_block(&cipher);
+        Ok(())
+    }
+}
+
+// a running Miyaguchi-Preneel hash over the events added since the pool was last drained.
+// events are zero padded to whole blocks, so the hash is only finalized on drain.
+#[derive(Debug, Clone, Default)]
+struct Pool {
+    chain: Block,
+    hashed: u64,
+    len: usize,
+}
+
+impl Pool {
+    fn add(&mut self, source_id: u8, data: &[u8]) {
+        let mut event: Vec<u8> = Vec::with_capacity(BLOCK_SIZE + MAX_EVENT);
+        event.push(source_id);
+        event.push(data.len() as u8);
+        event.extend_from_slice(data);
+        event.resize(event.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, 0);
+
+        self.chain = hash::md::iterate(POOL_HASH, self.chain, &event);
+        self.hashed += event.len() as u64;
+        self.len += data.len();
+    }
+
+    // the same 0x80 and bit length padding as BlockHash, which fits one block after whole blocks.
+    fn drain(&mut self) -> Block {
+        let pool: _ = std::mem::take(self);
+        let mut last: Block = [0; BLOCK_SIZE];
+        last[0] = 0x80;
+        last[BLOCK_SIZE - 8..].copy_from_slice(&(pool.hashed * 8).to_be_bytes());
+        POOL_HASH.compress(&pool.chain, &last)
+    }
+}
+
+pub struct Fortuna<E> {
+    source: E,
+    generator: Generator,
+    pools: Vec<Pool>,
+    reseed_count: u64,
+    last_reseed: Option<time::Instant>,
+    reseed_delay: time::Duration,
+    next_poll: usize,
+}
+
+impl<E: rng::EntropySource> Fortuna<E> {
+    pub fn new(source: E) -> Self {
+        Self {
+            source,
+            generator: Generator::default(),
+            pools: vec![Pool::default(); POOL_COUNT],
+            reseed_count: 0,
+            last_reseed: None,
+            reseed_delay: RESEED_DELAY,
+            next_poll: 0,
+        }
+    }
+
+    // the minimum time between reseeds, 100ms by default.
+    pub fn reseed_delay(mut self, delay: time::Duration) -> Self {
+        self.reseed_delay = delay;
+        self
+    }
+
+    // adds between 1 and 32 bytes from an event source to one of the 32 pools.
+    // sources are expected to cycle through the pools themselves.
+    pub fn add_event(&mut self, source_id: u8, pool: usize, data: &[u8]) -> Result<(), Error> {
+        if pool >= POOL_COUNT {
+            return Err(Error::InvalidParameter("fortuna has 32 pools"))
+        }
+        match data.len() {
+            0 => Err(Error::InputTooShort { len: 0, min: 1 }),
+            len if len > MAX_EVENT => Err(Error::InputTooLong { len, max: MAX_EVENT }),
+            _ => {
+                self.pools[pool].add(source_id, data);
+                Ok(())
+            }
+        }
+    }
+
+    // reads 32 bytes from the entropy source into the next pool in turn.
+    pub fn poll(&mut self) -> Result<(), Error> {
+        let mut buf: [u8; MAX_EVENT] = [0; MAX_EVENT];
+        self.source.fill_entropy(&mut buf)?;
+        let pool: _ = self.next_poll;
+        self.next_poll = (pool + 1) % POOL_COUNT;
+        self.add_event(POLL_SOURCE_ID, pool, &buf)
+    }
+
+    pub fn random_data(&mut self, out: &mut [u8]) -> Result<(), Error> {
+        // an unseeded generator polls the source until the first pool can seed it.
+        while !self.generator.is_seeded() && self.pools[0].len < MIN_POOL_SIZE {
+            self.poll()?
+        }
+
+        let delay_passed: _ = self.last_reseed
+            .is_none_or(|last| last.elapsed() >= self.reseed_delay);
+        if delay_passed && self.generator.is_seeded() {
+            self.poll()?
+        }
+        if self.pools[0].len >= MIN_POOL_SIZE && delay_passed {
+            self.reseed();
+        }
+        self.generator.pseudo_random_data(out)
+    }
+
+    // writes 64 bytes of fresh output to a temporary file readable only by the owner,
+    // syncs it and renames it over the seed file, so a crash never leaves a partial seed.
+    pub fn write_seed_file<P: AsRef<path::Path>>(&mut self, path: P) -> Result<(), Error> {
+        let mut seed: [u8; SEED_FILE_LEN] = [0; SEED_FILE_LEN];
+        self.random_data(&mut seed)?;
+
+        let mut tmp: ffi::OsString = path.as_ref().as_os_str().to_owned();
+        tmp.push(".tmp");
+        let mut options: _ = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut file: _ = options.open(&tmp)?;
+        file.write_all(&seed)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    // reseeds the generator from a seed file and immediately replaces it, so the same
+    // seed is never used twice.
+    pub fn update_seed_file<P: AsRef<path::Path>>(&mut self, path: P) -> Result<(), Error> {
+        let seed: _ = fs::read(&path)?;
+        if seed.len() != SEED_FILE_LEN {
+            return Err(Error::InvalidParameter("a seed file holds exactly 64 bytes"))
+        }
+        self.generator.reseed(&seed);
+        self.write_seed_file(path)
+    }
+
+    // pool i contributes to reseed r when 2^i divides r.
+    fn reseed(&mut self) {
+        self.reseed_count += 1;
+        let mut seed: Vec<u8> = Vec::with_capacity(POOL_COUNT * BLOCK_SIZE);
+        for (idx, pool) in self.pools.iter_mut().enumerate() {
+            if idx > 0 && !self.reseed_count.is_multiple_of(1 << idx) {
+                break
+            }
+            seed.extend_from_slice(&pool.drain());
+        }
+        self.generator.reseed(&seed);
+        self.last_reseed = Some(time::Instant::now());
+    }
+}
+
+impl<E: rng::EntropySource> rng::Rng for Fortuna<E> {
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        for chunk in dest.chunks_mut(MAX_REQUEST) {
+            self.random_data(chunk)?
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Rng;
+
+    fn counting() -> impl FnMut(&mut [u8]) -> Result<(), Error> {
+        let mut next: u8 = 0;
+        move |buf: &mut [u8]| {
+            for val in buf.iter_mut() {
+                *val = next;
+                next = next.wrapping_add(1);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_unseeded() {
+        let source: _ = |_: &mut [u8]| Err(Error::EntropyFailure);
+        let mut fortuna: _ = Fortuna::new(source);
+        let mut out: _ = [0; 16];
+        assert_eq!(Err(Error::EntropyFailure), fortuna.random_data(&mut out));
+    }
+
+    #[test]
+    fn test_add_event() {
+        let mut fortuna: _ = Fortuna::new(rng::OsEntropy);
+        assert!(fortuna.add_event(1, 31, &[0; 32]).is_ok());
+        assert_eq!(Err(Error::InvalidParameter("fortuna has 32 pools")), fortuna.add_event(1, 32, &[0]));
+        assert_eq!(Err(Error::InputTooShort { len: 0, min: 1 }), fortuna.add_event(1, 0, &[]));
+        assert_eq!(Err(Error::InputTooLong { len: 33, max: 32 }), fortuna.add_event(1, 0, &[0; 33]));
+        assert_eq!(32, fortuna.pools[31].len);
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let mut first: _ = Fortuna::new(counting());
+        let mut second: _ = Fortuna::new(counting());
+        let (mut lhs, mut rhs): _ = ([0; 48], [0; 48]);
+        first.random_data(&mut lhs).unwrap();
+        second.random_data(&mut rhs).unwrap();
+        assert_eq!(lhs, rhs);
+
+        // a caller event changes the pool digest and so the reseeded key.
+        first.add_event(7, 0, b"caller supplied event").unwrap();
+        second.add_event(7, 0, b"another event").unwrap();
+        let delay: _ = time::Duration::from_secs(0);
+        let (mut first, mut second): _ = (first.reseed_delay(delay), second.reseed_delay(delay));
+        for _ in 0..2 {
+            first.add_event(7, 0, &[0; 32]).unwrap();
+            second.add_event(7, 0, &[0; 32]).unwrap();
+        }
+        first.random_data(&mut lhs).unwrap();
+        second.random_data(&mut rhs).unwrap();
+        assert_ne!(lhs, rhs);
+    }
+
+    #[test]
+    fn test_pool_hash() {
+        let mut pool: _ = Pool::default();
+        pool.add(3, b"abc");
+        pool.add(4, &[0x5a; 32]);
+
+        let mut events: Vec<u8> = vec![3, 3, b'a', b'b', b'c'];
+        events.resize(BLOCK_SIZE, 0);
+        events.extend_from_slice(&[4, 32]);
+        events.extend_from_slice(&[0x5a; 32]);
+        events.resize(4 * BLOCK_SIZE, 0);
+        assert_eq!(hash::BlockHash::new(POOL_HASH).hash(&events), pool.drain());
+        assert_eq!(0, pool.len);
+    }
+
+    #[test]
+    fn test_poll_on_reseed() {
+        let mut fortuna: _ = Fortuna::new(counting()).reseed_delay(time::Duration::from_secs(0));
+        let mut out: _ = [0; 16];
+        fortuna.random_data(&mut out).unwrap();
+        let polled: _ = fortuna.next_poll;
+        fortuna.random_data(&mut out).unwrap();
+        assert_eq!((polled + 1) % POOL_COUNT, fortuna.next_poll);
+
+        // within the delay the source is left alone.
+        let mut fortuna: _ = fortuna.reseed_delay(time::Duration::from_secs(3600));
+        fortuna.random_data(&mut out).unwrap();
+        assert_eq!((polled + 1) % POOL_COUNT, fortuna.next_poll);
+    }
+
+    #[test]
+    fn test_generator() {
+        let mut generator: _ = Generator::default();
+        generator.reseed(b"seed");
+        let (key, counter): _ = (generator.key, generator.counter);
+
+        let mut out: _ = [0; 20];
+        generator.pseudo_random_data(&mut out).unwrap();
+
+        // counter mode output, then the following counter block becomes the new key.
+        let cipher: _ = RjindaelCipher::new(key);
+        let mut exp: Vec<u8> = Vec::new();
+        for idx in 0..3u128 {
+            let mut block: Block = (counter + idx).to_be_bytes();
+            cipher.encrypt_block(&mut block);
+            exp.extend_from_slice(&block);
+        }
+        assert_eq!(exp[..20], out[..]);
+        assert_eq!(exp[32..], generator.key[..]);
+        assert_eq!(counter + 3, generator.counter);
+
+        let mut large: _ = vec![0; MAX_REQUEST + 1];
+        assert_eq!(
+            Err(Error::OutputTooLong { len: MAX_REQUEST + 1, max: MAX_REQUEST }),
+            generator.pseudo_random_data(&mut large)
+        );
+    }
+
+    #[test]
+    fn test_reseed_schedule() {
+        let mut fortuna: _ = Fortuna::new(counting()).reseed_delay(time::Duration::from_secs(0));
+        let mut out: _ = [0; 16];
+        for reseed in 1..=8u64 {
+            for pool in 0..4 {
+                fortuna.add_event(1, pool, &[0; 32]).unwrap();
+                fortuna.add_event(1, pool, &[0; 32]).unwrap();
+            }
+            fortuna.random_data(&mut out).unwrap();
+            assert_eq!(reseed, fortuna.reseed_count);
+
+            // every pool whose turn it was has been drained, the rest keep accumulating.
+            for pool in 1..4 {
+                let drained: _ = reseed.is_multiple_of(1 << pool);
+                assert_eq!(drained, fortuna.pools[pool].len == 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reseed_delay() {
+        // a delay no test run outlasts suppresses the second reseed, one that has always
+        // elapsed lets it through.
+        for (delay, reseeds) in [(time::Duration::from_secs(3600), 1), (time::Duration::from_secs(0), 2)].iter() {
+            let mut fortuna: _ = Fortuna::new(counting()).reseed_delay(*delay);
+            let mut out: _ = [0; 16];
+            fortuna.random_data(&mut out).unwrap();
+            fortuna.add_event(1, 0, &[0; 32]).unwrap();
+            fortuna.add_event(1, 0, &[0; 32]).unwrap();
+            fortuna.random_data(&mut out).unwrap();
+            assert_eq!(*reseeds, fortuna.reseed_count);
+        }
+    }
+
+    #[test]
+    fn test_seed_file() {
+        let path: _ = std::env::temp_dir().join(format!("aes-fortuna-seed-{}", std::process::id()));
+        let mut fortuna: _ = Fortuna::new(rng::OsEntropy);
+        fortuna.write_seed_file(&path).unwrap();
+        let written: _ = fs::read(&path).unwrap();
+        assert_eq!(SEED_FILE_LEN, written.len());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(0o600, fs::metadata(&path).unwrap().permissions().mode() & 0o777);
+        }
+
+        let key: _ = fortuna.generator.key;
+        fortuna.update_seed_file(&path).unwrap();
+        assert_ne!(key, fortuna.generator.key);
+        assert_ne!(written, fs::read(&path).unwrap());
+
+        fs::write(&path, [0; 10]).unwrap();
+        assert!(fortuna.update_seed_file(&path).is_err());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Err(Error::Io(std::io::ErrorKind::NotFound)), fortuna.update_seed_file(&path));
+    }
+
+    #[test]
+    fn test_rng() {
+        let mut fortuna: _ = Fortuna::new(rng::OsEntropy);
+        assert_ne!(fortuna.gen_block(), fortuna.gen_block());
+    }
+}
@@ -0,0 +1,25 @@
+mod ctr_drbg;
+mod entropy;
+mod fortuna;
+
+pub use ctr_drbg::{CtrDrbg, Derivation};
+pub use entropy::{EntropySource, OsEntropy};
+pub use fortuna::{Fortuna, POLL_SOURCE_ID};
+
+use crate::{Block, BLOCK_SIZE, Error};
+
+// a source of cryptographically strong bytes for IVs, nonces and keys.
+pub trait Rng {
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error>;
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest)
+            .expect("attempting to fill bytes from a random generator that could not be reseeded.")
+    }
+
+    fn gen_block(&mut self) -> Block {
+        let mut buf: Block = [0; BLOCK_SIZE];
+        self.fill_bytes(&mut buf);
+        buf
+    }
+}
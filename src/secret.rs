@@ -0,0 +1,175 @@
+//! Values encrypted while the crate using them is compiled and decrypted on first use, the
+//! run time half of the aes-macros crate. The key is compiled in next to the ciphertext,
+//! so this keeps plaintext out of a binary's strings, not out of reach of anyone reading
+//! its code. The decrypted copy is overwritten with zeros when the value is dropped.
+
+use core::{cell::OnceCell, fmt, ops, str};
+
+use crate::{boxes, expand_key_const, modes, zeroize, Block, RjindaelCipher, BLOCK_SIZE};
+
+// named by the aes-macros expansion when AES_MACRO_KEY is unset, so that the build warns.
+#[doc(hidden)]
+#[deprecated(note = "AES_MACRO_KEY is not set, so this literal is encrypted under a random build key and the build is not reproducible")]
+pub const RANDOM_BUILD_KEY: () = ();
+
+// counter mode as in modes::Ctr, in const context. N is the length of data.
+pub const fn ctr_const<const N: usize>(key: Block, iv: Block, data: &[u8]) -> [u8; N] {
+    assert!(data.len() == N, "attempting to encrypt in const context: the output length differs from the input.");
+    let keys: [Block; 11] = expand_key_const(key);
+    let mut out: [u8; N] = [0; N];
+    let mut counter: u128 = u128::from_be_bytes(iv);
+    let mut offset: usize = 0;
+    while offset < N {
+        let keystream: Block = boxes::encrypt_const(&keys, counter.to_be_bytes());
+        let mut idx: usize = 0;
+        while idx < BLOCK_SIZE && offset + idx < N {
+            out[offset + idx] = data[offset + idx] ^ keystream[idx];
+            idx += 1;
+        }
+        counter = counter.wrapping_add(1);
+        offset += BLOCK_SIZE;
+    }
+    out
+}
+
+pub struct EncryptedBytes<const N: usize> {
+    key: Block,
+    iv: Block,
+    ciphertext: [u8; N],
+    plain: OnceCell<[u8; N]>,
+}
+
+impl<const N: usize> EncryptedBytes<N> {
+    pub const fn new(key: Block, iv: Block, ciphertext: [u8; N]) -> Self {
+        Self { key, iv, ciphertext, plain: OnceCell::new() }
+    }
+
+    pub fn ciphertext(&self) -> &[u8] {
+        &self.ciphertext
+    }
+
+    pub fn is_decrypted(&self) -> bool {
+        self.plain.get().is_some()
+    }
+
+    // the cell takes a copy of the plaintext, so the buffer it was decrypted in is cleared
+    // afterwards, along with the round keys.
+    pub fn get(&self) -> &[u8] {
+        if let Some(plain) = self.plain.get() {
+            return plain
+        }
+        let mut buf: [u8; N] = self.ciphertext;
+        let mut ctr: _ = modes::Ctr::new(RjindaelCipher::new(self.key), self.iv);
+        ctr.apply_keystream(&mut buf);
+        ctr.zeroize();
+
+        let plain: _ = self.plain.get_or_init(|| buf);
+        zeroize(&mut buf);
+        plain
+    }
+}
+
+impl<const N: usize> ops::Deref for EncryptedBytes<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.get()
+    }
+}
+
+// never shows the plaintext.
+impl<const N: usize> fmt::Debug for EncryptedBytes<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedBytes")
+            .field("len", &N)
+            .field("decrypted", &self.is_decrypted())
+            .finish()
+    }
+}
+
+impl<const N: usize> Drop for EncryptedBytes<N> {
+    fn drop(&mut self) {
+        if let Some(plain) = self.plain.get_mut() {
+            zeroize(plain)
+        }
+    }
+}
+
+// the bytes of a string literal, which are valid UTF-8 before and after the round trip.
+pub struct EncryptedStr<const N: usize>(EncryptedBytes<N>);
+
+impl<const N: usize> EncryptedStr<N> {
+    pub const fn new(key: Block, iv: Block, ciphertext: [u8; N]) -> Self {
+        Self(EncryptedBytes::new(key, iv, ciphertext))
+    }
+
+    pub fn ciphertext(&self) -> &[u8] {
+        self.0.ciphertext()
+    }
+
+    pub fn is_decrypted(&self) -> bool {
+        self.0.is_decrypted()
+    }
+
+    pub fn get(&self) -> &str {
+        str::from_utf8(self.0.get())
+            .expect("attempting to decrypt a string: the key or ciphertext does not belong to the literal.")
+    }
+}
+
+impl<const N: usize> ops::Deref for EncryptedStr<N> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.get()
+    }
+}
+
+impl<const N: usize> fmt::Debug for EncryptedStr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedStr")
+            .field("len", &N)
+            .field("decrypted", &self.is_decrypted())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const KEY: Block = *b"compile time key";
+    const IV: Block = [0xff; BLOCK_SIZE];
+    const PLAIN: &str = "https://example.invalid/v1/endpoint?token=1";
+    const CIPHER: [u8; PLAIN.len()] = ctr_const(KEY, IV, PLAIN.as_bytes());
+
+    #[test]
+    fn test_ctr_const() {
+        // the const keystream is the modes::Ctr one, across the wrap of the counter.
+        let mut exp: [u8; PLAIN.len()] = [0; PLAIN.len()];
+        exp.copy_from_slice(PLAIN.as_bytes());
+        modes::Ctr::new(RjindaelCipher::new(KEY), IV).apply_keystream(&mut exp);
+        assert_eq!(exp, CIPHER);
+        assert_eq!([0u8; 0], ctr_const::<0>(KEY, IV, &[]));
+    }
+
+    #[test]
+    fn test_lazy() {
+        let secret: _ = EncryptedStr::new(KEY, IV, CIPHER);
+        assert!(!secret.is_decrypted());
+        assert_eq!(&CIPHER[..], secret.ciphertext());
+        assert_eq!(PLAIN, &*secret);
+        assert!(secret.is_decrypted());
+        assert!(!format!("{:?}", secret).contains("example"));
+
+        let bytes: _ = EncryptedBytes::new(KEY, IV, CIPHER);
+        assert_eq!(PLAIN.as_bytes(), &*bytes);
+    }
+
+    #[test]
+    fn test_zeroize() {
+        let mut buf: _ = *b"secret";
+        zeroize(&mut buf);
+        assert_eq!([0; 6], buf);
+    }
+}
@@ -0,0 +1,496 @@
+//! Substitution-permutation networks assembled from a 4- or 8-bit S-box, an optional row
+//! shift, a linear layer and a key schedule closure. The state is a grid of cells filled
+//! column by column as in AES, so cell i sits in row i % rows of column i / rows, or row by
+//! row as in LED, and the block's bytes map to cells most significant first. Each round adds
+//! its key, substitutes, shifts and applies the linear layer, and the last round key is added
+//! after the last round.
+
+use std::fmt;
+
+use crate::{mds, Error};
+
+// one per cipher, so the size of the matrix's field tables does not matter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
+pub enum Linear {
+    None,
+    // the matrix times each column, which must have as many cells as the matrix has rows.
+    // Its field must be as wide as the cells, so 4-bit cells take a field such as GF(16).
+    Matrix(mds::Matrix),
+    // bit i of the block, counting from the least significant, moves to bit p[i].
+    BitPermutation(Vec<usize>),
+}
+
+// the round keys for rounds + 1 key additions, each the size of a block.
+type KeySchedule = Box<dyn Fn(&[u8]) -> Vec<Vec<u8>>>;
+
+pub struct SpnBuilder {
+    cells: usize,
+    sbox: Vec<u8>,
+    rounds: usize,
+    shifts: Vec<usize>,
+    linear: Linear,
+    skip_last_linear: bool,
+    row_major: bool,
+    key_schedule: KeySchedule,
+}
+
+impl fmt::Debug for SpnBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpnBuilder")
+            .field("cells", &self.cells)
+            .field("rounds", &self.rounds)
+            .field("shifts", &self.shifts)
+            .field("linear", &self.linear)
+            .field("skip_last_linear", &self.skip_last_linear)
+            .field("row_major", &self.row_major)
+            .finish()
+    }
+}
+
+impl SpnBuilder {
+    // by default every round key is the key itself.
+    pub fn new(cells: usize, sbox: &[u8], rounds: usize) -> Self {
+        Self {
+            cells,
+            sbox: sbox.to_vec(),
+            rounds,
+            shifts: vec![0],
+            linear: Linear::None,
+            skip_last_linear: false,
+            row_major: false,
+            key_schedule: Box::new(move |key: &[u8]| vec![key.to_vec(); rounds + 1]),
+        }
+    }
+
+    // row r of the grid rotates left by shifts[r] cells, one row per entry.
+    pub fn shift_rows(mut self, shifts: Vec<usize>) -> Self {
+        self.shifts = shifts;
+        self
+    }
+
+    pub fn linear(mut self, linear: Linear) -> Self {
+        self.linear = linear;
+        self
+    }
+
+    // leaves the linear layer out of the last round, as AES does.
+    pub fn skip_last_linear(mut self, skip: bool) -> Self {
+        self.skip_last_linear = skip;
+        self
+    }
+
+    // fills the grid row by row, so cell i sits in row i / cols of column i % cols.
+    pub fn row_major(mut self, row_major: bool) -> Self {
+        self.row_major = row_major;
+        self
+    }
+
+    pub fn key_schedule<F>(mut self, schedule: F) -> Self
+    where
+        F: Fn(&[u8]) -> Vec<Vec<u8>> + 'static
+    {
+        self.key_schedule = Box::new(schedule);
+        self
+    }
+
+    pub fn build(&self, key: &[u8]) -> Result<Spn, Error> {
+        let cell_bits: _ = match self.sbox.len() {
+            16 => 4,
+            256 => 8,
+            _ => return Err(Error::InvalidParameter("the S-box must map 4 or 8 bits")),
+        };
+        let rows: _ = self.shifts.len();
+        if self.cells == 0 || rows == 0 || !self.cells.is_multiple_of(rows) || !(self.cells * cell_bits).is_multiple_of(8) || self.cells * cell_bits > 128 {
+            return Err(Error::InvalidParameter("the cells do not make a grid of whole bytes up to 128 bits"))
+        }
+        let table: Vec<usize> = self.sbox.iter().map(|val| *val as usize).collect();
+        let rsbox: Vec<u8> = invert(&table)
+            .ok_or(Error::InvalidParameter("the S-box is not a permutation"))?
+            .into_iter()
+            .map(|val| val as u8)
+            .collect();
+
+        let inverse: _ = match &self.linear {
+            Linear::None => Linear::None,
+            Linear::Matrix(matrix) => {
+                if matrix.field().degree() as usize != cell_bits || !matrix.in_field() || matrix.size() != rows {
+                    return Err(Error::InvalidParameter("the matrix needs a field as wide as the cells and one row per grid row"))
+                }
+                Linear::Matrix(matrix.inverse()?)
+            },
+            Linear::BitPermutation(perm) => {
+                if perm.len() != self.cells * cell_bits {
+                    return Err(Error::InvalidParameter("the bit permutation does not cover the block"))
+                }
+                Linear::BitPermutation(invert(perm).ok_or(Error::InvalidParameter("the bit permutation is not a permutation"))?)
+            },
+        };
+
+        let block_size: _ = self.cells * cell_bits / 8;
+        let keys: _ = (self.key_schedule)(key);
+        if keys.len() != self.rounds + 1 || keys.iter().any(|rkey| rkey.len() != block_size) {
+            return Err(Error::InvalidParameter("the key schedule must give rounds + 1 keys of a block each"))
+        }
+
+        Ok(Spn {
+            cell_bits,
+            rows,
+            block_size,
+            sbox: self.sbox.clone(),
+            rsbox,
+            shifts: self.shifts.clone(),
+            linear: self.linear.clone(),
+            inverse,
+            skip_last_linear: self.skip_last_linear,
+            row_major: self.row_major,
+            keys: keys.iter().map(|rkey| to_cells(rkey, cell_bits)).collect(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spn {
+    cell_bits: usize,
+    rows: usize,
+    block_size: usize,
+    sbox: Vec<u8>,
+    rsbox: Vec<u8>,
+    shifts: Vec<usize>,
+    linear: Linear,
+    inverse: Linear,
+    skip_last_linear: bool,
+    row_major: bool,
+    keys: Vec<Vec<u8>>,
+}
+
+impl Spn {
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.keys.len() - 1
+    }
+
+    pub fn encrypt(&self, block: &mut [u8]) -> Result<(), Error> {
+        self.encrypt_rounds(block, self.rounds())
+    }
+
+    pub fn decrypt(&self, block: &mut [u8]) -> Result<(), Error> {
+        self.decrypt_rounds(block, self.rounds())
+    }
+
+    // the first rounds rounds then round key rounds, with the last linear layer left out
+    // of the last round run when the cipher skips it.
+    pub fn encrypt_rounds(&self, block: &mut [u8], rounds: usize) -> Result<(), Error> {
+        self.check(block, rounds)?;
+        let mut state: _ = to_cells(block, self.cell_bits);
+        self.forward(&mut state, rounds, |_| ());
+        block.copy_from_slice(&from_cells(&state, self.cell_bits));
+        Ok(())
+    }
+
+    pub fn decrypt_rounds(&self, block: &mut [u8], rounds: usize) -> Result<(), Error> {
+        self.check(block, rounds)?;
+        let mut state: _ = to_cells(block, self.cell_bits);
+        xor(&mut state, &self.keys[rounds]);
+        for round in (0..rounds).rev() {
+            if !(self.skip_last_linear && round == rounds - 1) {
+                self.apply_linear(&mut state, &self.inverse)
+            }
+            self.shift(&mut state, false);
+            state.iter_mut().for_each(|cell| *cell = self.rsbox[*cell as usize]);
+            xor(&mut state, &self.keys[round]);
+        }
+        block.copy_from_slice(&from_cells(&state, self.cell_bits));
+        Ok(())
+    }
+
+    // the state at the start of every round, after its key is added, then the ciphertext.
+    pub fn trace(&self, block: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+        self.check(block, self.rounds())?;
+        let mut buf: Vec<Vec<u8>> = Vec::with_capacity(self.rounds() + 1);
+        let mut state: _ = to_cells(block, self.cell_bits);
+        self.forward(&mut state, self.rounds(), |state| buf.push(from_cells(state, self.cell_bits)));
+        buf.push(from_cells(&state, self.cell_bits));
+        Ok(buf)
+    }
+
+    fn forward<F: FnMut(&[u8])>(&self, state: &mut Vec<u8>, rounds: usize, mut trace: F) {
+        for round in 0..rounds {
+            xor(state, &self.keys[round]);
+            trace(state);
+            state.iter_mut().for_each(|cell| *cell = self.sbox[*cell as usize]);
+            self.shift(state, true);
+            if !(self.skip_last_linear && round == rounds - 1) {
+                self.apply_linear(state, &self.linear)
+            }
+        }
+        xor(state, &self.keys[rounds]);
+    }
+
+    fn check(&self, block: &[u8], rounds: usize) -> Result<(), Error> {
+        if block.len() != self.block_size {
+            return Err(Error::InvalidParameter("the block is not the cipher's block size"))
+        }
+        if rounds > self.rounds() {
+            return Err(Error::InvalidParameter("the cipher has fewer rounds than requested"))
+        }
+        Ok(())
+    }
+
+    // the index of the cell in the row and column of a grid with cols columns.
+    fn cell(&self, row: usize, col: usize, cols: usize) -> usize {
+        match self.row_major {
+            true => row * cols + col,
+            false => col * self.rows + row,
+        }
+    }
+
+    fn shift(&self, state: &mut [u8], forward: bool) {
+        let cols: _ = state.len() / self.rows;
+        let old: _ = state.to_vec();
+        for (row, shift) in self.shifts.iter().enumerate() {
+            for col in 0..cols {
+                let from: _ = if forward { (col + shift) % cols } else { (col + cols - shift % cols) % cols };
+                state[self.cell(row, col, cols)] = old[self.cell(row, from, cols)]
+            }
+        }
+    }
+
+    fn apply_linear(&self, state: &mut Vec<u8>, linear: &Linear) {
+        match linear {
+            Linear::None => (),
+            Linear::Matrix(matrix) => {
+                let cols: _ = state.len() / self.rows;
+                for col in 0..cols {
+                    let column: Vec<u8> = (0..self.rows).map(|row| state[self.cell(row, col, cols)]).collect();
+                    for (row, val) in matrix.apply(&column).into_iter().enumerate() {
+                        state[self.cell(row, col, cols)] = val
+                    }
+                }
+            },
+            Linear::BitPermutation(perm) => {
+                let bits: _ = perm.len();
+                let val: _ = to_int(state, self.cell_bits);
+                let mut out: u128 = 0;
+                for (idx, dest) in perm.iter().enumerate() {
+                    out |= ((val >> idx) & 1) << dest
+                }
+                *state = from_int(out, bits / self.cell_bits, self.cell_bits)
+            },
+        }
+    }
+}
+
+fn invert(table: &[usize]) -> Option<Vec<usize>> {
+    let mut buf: Vec<Option<usize>> = vec![None; table.len()];
+    for (idx, val) in table.iter().enumerate() {
+        let slot: _ = buf.get_mut(*val)?;
+        if slot.is_some() {
+            return None
+        }
+        *slot = Some(idx);
+    }
+    buf.into_iter().collect()
+}
+
+fn xor(state: &mut [u8], rkey: &[u8]) {
+    state.iter_mut().zip(rkey.iter()).for_each(|(cell, key)| *cell ^= key)
+}
+
+fn to_cells(bytes: &[u8], cell_bits: usize) -> Vec<u8> {
+    match cell_bits {
+        4 => bytes.iter().flat_map(|byte| vec![byte >> 4, byte & 0x0f]).collect(),
+        _ => bytes.to_vec(),
+    }
+}
+
+fn from_cells(cells: &[u8], cell_bits: usize) -> Vec<u8> {
+    match cell_bits {
+        4 => cells.chunks(2).map(|pair| (pair[0] << 4) | pair[1]).collect(),
+        _ => cells.to_vec(),
+    }
+}
+
+fn to_int(cells: &[u8], cell_bits: usize) -> u128 {
+    cells.iter().fold(0, |acc, cell| (acc << cell_bits) | *cell as u128)
+}
+
+fn from_int(val: u128, cells: usize, cell_bits: usize) -> Vec<u8> {
+    let mask: _ = (1u128 << cell_bits) - 1;
+    (0..cells).rev().map(|idx| ((val >> (idx * cell_bits)) & mask) as u8).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{primitives::sbox, test_util::unhex, Field};
+
+    const PRESENT_SBOX: [u8; 16] = [0xc, 0x5, 0x6, 0xb, 0x9, 0x0, 0xa, 0xd, 0x3, 0xe, 0xf, 0x8, 0x4, 0x7, 0x1, 0x2];
+
+    // PRESENT-80: 31 rounds, the bit permutation 16i mod 63, and round keys taken from the
+    // top of an 80-bit register that rotates, substitutes and adds the round counter.
+    fn present() -> SpnBuilder {
+        let perm: Vec<usize> = (0..64).map(|idx| if idx == 63 { 63 } else { (16 * idx) % 63 }).collect();
+        SpnBuilder::new(16, &PRESENT_SBOX, 31)
+            .linear(Linear::BitPermutation(perm))
+            .key_schedule(|key| {
+                let mut reg: u128 = key.iter().fold(0, |acc, byte| (acc << 8) | *byte as u128);
+                let mask: u128 = (1 << 80) - 1;
+                let mut keys: Vec<Vec<u8>> = Vec::new();
+                for round in 1..=32u128 {
+                    keys.push(((reg >> 16) as u64).to_be_bytes().to_vec());
+                    reg = ((reg << 61) | (reg >> 19)) & mask;
+                    reg = (reg & !(0xf << 76)) | ((PRESENT_SBOX[(reg >> 76) as usize] as u128) << 76);
+                    reg ^= round << 15;
+                }
+                keys
+            })
+    }
+
+    // AES-128 itself, which checks the grid, shift and matrix conventions against FIPS-197.
+    fn aes() -> SpnBuilder {
+        SpnBuilder::new(16, &sbox::SUB_BOX, 10)
+            .shift_rows(vec![0, 1, 2, 3])
+            .linear(Linear::Matrix(mds::Matrix::circulant(&[0x02, 0x03, 0x01, 0x01]).unwrap()))
+            .skip_last_linear(true)
+            .key_schedule(|key| {
+                let mut words: Vec<[u8; 4]> = key.chunks(4).map(|chunk| [chunk[0], chunk[1], chunk[2], chunk[3]]).collect();
+                let mut rcon: u8 = 1;
+                for idx in 4..44 {
+                    let mut tmp: [u8; 4] = words[idx - 1];
+                    if idx % 4 == 0 {
+                        tmp = [
+                            sbox::SUB_BOX[tmp[1] as usize] ^ rcon,
+                            sbox::SUB_BOX[tmp[2] as usize],
+                            sbox::SUB_BOX[tmp[3] as usize],
+                            sbox::SUB_BOX[tmp[0] as usize],
+                        ];
+                        rcon = Field::AES.mul(rcon, 2);
+                    }
+                    let prev: _ = words[idx - 4];
+                    words.push([prev[0] ^ tmp[0], prev[1] ^ tmp[1], prev[2] ^ tmp[2], prev[3] ^ tmp[3]]);
+                }
+                words.chunks(4).map(|chunk| chunk.concat()).collect()
+            })
+    }
+
+    // LED-64: 32 rounds of AddConstants, SubCells, ShiftRows and MixColumnsSerial over GF(16)
+    // on a grid filled row by row, with the key added before every fourth round and after the
+    // last. The constants go in the round keys: the key size 64 split over the first column
+    // and the 6-bit round counter over the second.
+    fn led() -> SpnBuilder {
+        let field: _ = Field::new(0x13, 0x02).unwrap();
+        let matrix: _ = mds::Matrix::new(4, vec![
+            0x4, 0x1, 0x2, 0x2,
+            0x8, 0x6, 0x5, 0x6,
+            0xb, 0xe, 0xa, 0x9,
+            0x2, 0x2, 0xf, 0xb
+        ]).unwrap().with_field(field);
+        SpnBuilder::new(16, &PRESENT_SBOX, 32)
+            .shift_rows(vec![0, 1, 2, 3])
+            .linear(Linear::Matrix(matrix))
+            .row_major(true)
+            .key_schedule(|key| {
+                let mut rc: u8 = 0;
+                let mut keys: Vec<Vec<u8>> = Vec::new();
+                for round in 0..32 {
+                    rc = ((rc << 1) & 0x3f) | (((rc >> 5) ^ (rc >> 4) ^ 1) & 1);
+                    let mut rkey: Vec<u8> = vec![0; 8];
+                    for row in 0..4 {
+                        let size: u8 = if row < 2 { 0x4 } else { 0x0 };
+                        let counter: u8 = if row % 2 == 0 { rc >> 3 } else { rc & 0x7 };
+                        rkey[2 * row] = ((row as u8 ^ size) << 4) | counter;
+                    }
+                    if round % 4 == 0 {
+                        xor(&mut rkey, key)
+                    }
+                    keys.push(rkey);
+                }
+                keys.push(key.to_vec());
+                keys
+            })
+    }
+
+    #[test]
+    fn test_present() {
+        // the four test vectors of the PRESENT paper, appendix I.
+        let vectors: _ = [
+            ("00000000000000000000", "0000000000000000", "5579c1387b228445"),
+            ("ffffffffffffffffffff", "0000000000000000", "e72c46c0f5945049"),
+            ("00000000000000000000", "ffffffffffffffff", "a112ffc72f68417b"),
+            ("ffffffffffffffffffff", "ffffffffffffffff", "3333dcd3213210d2"),
+        ];
+        for (key, plain, exp) in vectors.iter() {
+            let cipher: _ = present().build(&unhex(key)).unwrap();
+            let mut test: _ = unhex(plain);
+            cipher.encrypt(&mut test).unwrap();
+            assert_eq!(unhex(exp), test);
+            cipher.decrypt(&mut test).unwrap();
+            assert_eq!(unhex(plain), test);
+        }
+    }
+
+    #[test]
+    fn test_aes() {
+        // FIPS-197 appendix B, with the start of rounds 1 and 2 from its trace.
+        let cipher: _ = aes().build(&unhex("2b7e151628aed2a6abf7158809cf4f3c")).unwrap();
+        let plain: _ = unhex("3243f6a8885a308d313198a2e0370734");
+        let trace: _ = cipher.trace(&plain).unwrap();
+        assert_eq!(11, trace.len());
+        assert_eq!(unhex("193de3bea0f4e22b9ac68d2ae9f84808"), trace[0]);
+        assert_eq!(unhex("a49c7ff2689f352b6b5bea43026a5049"), trace[1]);
+        assert_eq!(unhex("3925841d02dc09fbdc118597196a0b32"), trace[10]);
+
+        let mut test: _ = plain.clone();
+        cipher.encrypt(&mut test).unwrap();
+        assert_eq!(trace[10], test);
+        cipher.decrypt(&mut test).unwrap();
+        assert_eq!(plain, test);
+    }
+
+    #[test]
+    fn test_led() {
+        // the LED-64 test vectors of the LED paper.
+        let vectors: _ = [
+            ("0000000000000000", "0000000000000000", "39c2401003a0c798"),
+            ("0123456789abcdef", "0123456789abcdef", "a003551e3893fc58"),
+        ];
+        for (key, plain, exp) in vectors.iter() {
+            let cipher: _ = led().build(&unhex(key)).unwrap();
+            let mut test: _ = unhex(plain);
+            cipher.encrypt(&mut test).unwrap();
+            assert_eq!(unhex(exp), test);
+            cipher.decrypt(&mut test).unwrap();
+            assert_eq!(unhex(plain), test);
+        }
+
+        let cipher: _ = led().build(&unhex("0123456789abcdef")).unwrap();
+        let plain: _ = unhex("0123456789abcdef");
+        for rounds in 0..=32 {
+            let mut test: _ = plain.clone();
+            cipher.encrypt_rounds(&mut test, rounds).unwrap();
+            cipher.decrypt_rounds(&mut test, rounds).unwrap();
+            assert_eq!(plain, test);
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let key: _ = [0; 16];
+        assert!(SpnBuilder::new(16, &[0; 16], 4).build(&[0; 8]).is_err());
+        assert!(SpnBuilder::new(16, &[0; 10], 4).build(&[0; 8]).is_err());
+        assert!(SpnBuilder::new(15, &PRESENT_SBOX, 4).build(&[0; 8]).is_err());
+        let matrix: _ = mds::Matrix::circulant(&[0x02, 0x03, 0x01, 0x01]).unwrap();
+        assert!(SpnBuilder::new(32, &PRESENT_SBOX, 4).shift_rows(vec![0, 1, 2, 3]).linear(Linear::Matrix(matrix.clone())).build(&key).is_err());
+        // a GF(16) matrix on 8-bit cells, and entries outside GF(16).
+        let small: _ = matrix.with_field(Field::new(0x13, 0x02).unwrap());
+        assert!(SpnBuilder::new(16, &sbox::SUB_BOX, 4).shift_rows(vec![0, 1, 2, 3]).linear(Linear::Matrix(small)).build(&key).is_err());
+        let outside: _ = mds::Matrix::circulant(&[0x02, 0x1b, 0x01, 0x01]).unwrap().with_field(Field::new(0x13, 0x02).unwrap());
+        assert!(SpnBuilder::new(32, &PRESENT_SBOX, 4).shift_rows(vec![0, 1, 2, 3]).linear(Linear::Matrix(outside)).build(&key).is_err());
+        assert!(SpnBuilder::new(16, &sbox::SUB_BOX, 4).key_schedule(|_| vec![vec![0; 16]; 4]).build(&key).is_err());
+        assert!(SpnBuilder::new(16, &sbox::SUB_BOX, 4).build(&key).unwrap().encrypt(&mut [0; 15]).is_err());
+    }
+}
@@ -0,0 +1,75 @@
+//! Incremental encryption over std::io, for inputs too large to hold in memory.
+//! CBC streams are PKCS#7 padded; the padding is unauthenticated, so pair them with a MAC.
+
+mod reader;
+mod writer;
+
+pub use reader::DecryptReader;
+pub use writer::EncryptWriter;
+
+use crate::{modes, Block, Error, RjindaelCipher, BLOCK_SIZE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamMode {
+    Ctr,
+    Cbc,
+}
+
+// the CTR keystream is built once per stream. CBC chains from the last ciphertext block,
+// so it keeps the cipher and the running IV.
+enum Engine {
+    Ctr(modes::Ctr),
+    Cbc { cipher: RjindaelCipher, iv: Block },
+}
+
+impl Engine {
+    fn new(cipher: RjindaelCipher, iv: Block, mode: StreamMode) -> Self {
+        match mode {
+            StreamMode::Ctr => Engine::Ctr(modes::Ctr::new(cipher, iv)),
+            StreamMode::Cbc => Engine::Cbc { cipher, iv }
+        }
+    }
+}
+
+fn pad(buf: &mut Vec<u8>) {
+    let len: _ = BLOCK_SIZE - buf.len() % BLOCK_SIZE;
+    buf.resize(buf.len() + len, len as u8)
+}
+
+fn unpad(buf: &mut Vec<u8>) -> Result<(), Error> {
+    let len: _ = *buf.last().ok_or(Error::InvalidPadding)? as usize;
+    if len == 0 || len > BLOCK_SIZE || len > buf.len() {
+        return Err(Error::InvalidPadding)
+    }
+    match buf[buf.len() - len..].iter().all(|&byte| byte as usize == len) {
+        true => {
+            buf.truncate(buf.len() - len);
+            Ok(())
+        },
+        false => Err(Error::InvalidPadding)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_padding() {
+        let mut buf: _ = vec![0xaa; 13];
+        pad(&mut buf);
+        assert_eq!([0x03; 3], buf[13..]);
+        unpad(&mut buf).unwrap();
+        assert_eq!(vec![0xaa; 13], buf);
+
+        let mut full: _ = vec![0xaa; 16];
+        pad(&mut full);
+        assert_eq!(vec![0x10; 16], full[16..].to_vec());
+
+        assert_eq!(Err(Error::InvalidPadding), unpad(&mut vec![0xaa; 16]));
+        assert_eq!(Err(Error::InvalidPadding), unpad(&mut vec![0x00; 16]));
+        let mut mixed: _ = vec![0x02; 16];
+        mixed[14] = 0x01;
+        assert_eq!(Err(Error::InvalidPadding), unpad(&mut mixed));
+    }
+}
@@ -0,0 +1,185 @@
+use std::io;
+
+use crate::{modes, stream, to_block, Block, BLOCK_SIZE, Error, RjindaelCipher};
+use crate::stream::Engine;
+
+const READ_SIZE: usize = 4096;
+
+// decrypts everything read from the inner reader. CBC keeps the last block back until
+// the inner reader is exhausted, so the padding can be checked and removed.
+pub struct DecryptReader<R: io::Read> {
+    inner: R,
+    engine: Engine,
+    pos: u64,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    done: bool,
+}
+
+impl<R: io::Read> DecryptReader<R> {
+    pub fn new(inner: R, cipher: RjindaelCipher, iv: Block, mode: stream::StreamMode) -> Self {
+        Self {
+            inner,
+            engine: Engine::new(cipher, iv, mode),
+            pos: 0,
+            input: Vec::new(),
+            output: Vec::new(),
+            done: false
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    // decrypts every complete block except the last, or everything once the input ends.
+    // nothing is consumed until the blocks decrypt and unpad, so a bad final chunk fails
+    // every read rather than ending the stream.
+    fn fill(&mut self) -> io::Result<()> {
+        let mut chunk: [u8; READ_SIZE] = [0; READ_SIZE];
+        let len: _ = self.inner.read(&mut chunk)?;
+        self.input.extend_from_slice(&chunk[..len]);
+
+        let last: _ = len == 0;
+        let ready: _ = match last {
+            true if self.input.is_empty() => return Err(Error::InputTooShort { len: 0, min: BLOCK_SIZE }.into()),
+            true => self.input.len(),
+            false => self.input.len().saturating_sub(1) / BLOCK_SIZE * BLOCK_SIZE
+        };
+        if ready == 0 {
+            return Ok(())
+        }
+
+        let Engine::Cbc { cipher, iv } = &mut self.engine else {
+            unreachable!("only CBC streams hold back input")
+        };
+        let mut blocks: Vec<u8> = self.input[..ready].to_vec();
+        let next_iv: _ = match blocks.len() >= BLOCK_SIZE {
+            true => to_block(&blocks[blocks.len() - BLOCK_SIZE..]),
+            false => *iv
+        };
+        modes::Cbc::new(cipher.clone(), *iv).decrypt(&mut blocks)?;
+        if last {
+            stream::unpad(&mut blocks)?
+        }
+        self.input.drain(..ready);
+        *iv = next_iv;
+        self.done = last;
+        self.output.extend_from_slice(&blocks);
+        Ok(())
+    }
+}
+
+impl<R: io::Read> io::Read for DecryptReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        match &self.engine {
+            Engine::Ctr(ctr) => {
+                let len: _ = self.inner.read(out)?;
+                ctr.apply_keystream_at(self.pos, &mut out[..len]);
+                self.pos += len as u64;
+                Ok(len)
+            },
+            Engine::Cbc { .. } => {
+                while self.output.is_empty() && !self.done {
+                    self.fill()?
+                }
+                let len: _ = out.len().min(self.output.len());
+                out[..len].copy_from_slice(&self.output[..len]);
+                self.output.drain(..len);
+                Ok(len)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{Read, Write};
+
+    // hands out at most a few bytes per read, like a slow socket.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl io::Read for Trickle<'_> {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            let len: _ = out.len().min(self.0.len()).min(5);
+            out[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    fn cipher() -> RjindaelCipher {
+        RjindaelCipher::new(*b"streaming key 01")
+    }
+
+    fn encrypt(mode: stream::StreamMode, msg: &[u8]) -> Vec<u8> {
+        let mut writer: _ = stream::EncryptWriter::new(Vec::new(), cipher(), [0x24; 16], mode);
+        writer.write_all(msg).unwrap();
+        writer.finalize().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for mode in [stream::StreamMode::Ctr, stream::StreamMode::Cbc].iter() {
+            for len in [0, 1, 15, 16, 17, 5000].iter() {
+                let msg: Vec<u8> = (0..*len).map(|x| x as u8).collect();
+                let ct: _ = encrypt(*mode, &msg);
+
+                let mut reader: _ = DecryptReader::new(Trickle(&ct), cipher(), [0x24; 16], *mode);
+                let mut test: Vec<u8> = Vec::new();
+                reader.read_to_end(&mut test).unwrap();
+                assert_eq!(msg, test);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cbc_errors() {
+        let read: _ = |ct: &[u8]| {
+            let mut reader: _ = DecryptReader::new(ct, cipher(), [0x24; 16], stream::StreamMode::Cbc);
+            reader.read_to_end(&mut Vec::new()).unwrap_err().kind()
+        };
+        let ct: _ = encrypt(stream::StreamMode::Cbc, b"an unaligned message");
+        assert_eq!(io::ErrorKind::InvalidData, read(&ct[..ct.len() - 1]));
+        assert_eq!(io::ErrorKind::InvalidData, read(&[]));
+
+        // dropping the final block leaves a block whose padding does not check out.
+        assert_eq!(io::ErrorKind::InvalidData, read(&ct[..BLOCK_SIZE]));
+    }
+
+    #[test]
+    fn test_cbc_errors_persist() {
+        // a caller that retries after the error never sees a clean end of stream.
+        let ct: _ = encrypt(stream::StreamMode::Cbc, b"an unaligned message");
+        for bad in [&ct[..ct.len() - 1], &ct[..BLOCK_SIZE], &[][..]].iter() {
+            let mut reader: _ = DecryptReader::new(*bad, cipher(), [0x24; 16], stream::StreamMode::Cbc);
+            let mut buf: [u8; 64] = [0; 64];
+            // the blocks before the bad one may come out first.
+            let err: _ = (0..ct.len()).find_map(|_| reader.read(&mut buf).err()).unwrap();
+            assert_eq!(io::ErrorKind::InvalidData, err.kind());
+            for _ in 0..3 {
+                assert_eq!(io::ErrorKind::InvalidData, reader.read(&mut buf).unwrap_err().kind());
+            }
+        }
+    }
+
+    // fails every read with an error that carries more than its kind.
+    struct Broken;
+
+    impl io::Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::ConnectionReset, "peer went away"))
+        }
+    }
+
+    #[test]
+    fn test_inner_error() {
+        for mode in [stream::StreamMode::Ctr, stream::StreamMode::Cbc].iter() {
+            let mut reader: _ = DecryptReader::new(Broken, cipher(), [0x24; 16], *mode);
+            let err: _ = reader.read(&mut [0; 16]).unwrap_err();
+            assert_eq!(io::ErrorKind::ConnectionReset, err.kind());
+            assert_eq!("peer went away", err.to_string());
+        }
+    }
+}
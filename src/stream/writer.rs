@@ -0,0 +1,169 @@
+use std::io;
+
+use crate::{modes, stream, to_block, Block, BLOCK_SIZE, RjindaelCipher};
+use crate::stream::Engine;
+
+// encrypts everything written and passes it on to the inner writer. CTR output is
+// written straight through, CBC holds back a partial block until finalize pads it.
+pub struct EncryptWriter<W: io::Write> {
+    inner: W,
+    engine: Engine,
+    pos: u64,
+    pending: Vec<u8>,
+    // reused for the encrypted copy of each write.
+    buf: Vec<u8>,
+}
+
+impl<W: io::Write> EncryptWriter<W> {
+    pub fn new(inner: W, cipher: RjindaelCipher, iv: Block, mode: stream::StreamMode) -> Self {
+        Self {
+            inner,
+            engine: Engine::new(cipher, iv, mode),
+            pos: 0,
+            pending: Vec::with_capacity(BLOCK_SIZE),
+            buf: Vec::new()
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    // writes the final padded block, if any, and hands back the inner writer.
+    // dropping the writer without finalizing loses the held back data.
+    pub fn finalize(mut self) -> io::Result<W> {
+        if let Engine::Cbc { cipher, iv } = &self.engine {
+            let mut last: _ = std::mem::take(&mut self.pending);
+            stream::pad(&mut last);
+            modes::Cbc::new(cipher.clone(), *iv).encrypt(&mut last)?;
+            self.inner.write_all(&last)?;
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: io::Write> io::Write for EncryptWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.clear();
+        match &mut self.engine {
+            Engine::Ctr(ctr) => {
+                self.buf.extend_from_slice(data);
+                ctr.apply_keystream_at(self.pos, &mut self.buf);
+                self.inner.write_all(&self.buf)?;
+                self.pos += data.len() as u64;
+            },
+            Engine::Cbc { cipher, iv } => {
+                // the chain and the held back bytes only move on once the blocks are written,
+                // so a failed write can be retried with the same data.
+                let full: _ = (self.pending.len() + data.len()) / BLOCK_SIZE * BLOCK_SIZE;
+                if full == 0 {
+                    self.pending.extend_from_slice(data);
+                    return Ok(data.len())
+                }
+                let take: _ = full - self.pending.len();
+                self.buf.extend_from_slice(&self.pending);
+                self.buf.extend_from_slice(&data[..take]);
+                modes::Cbc::new(cipher.clone(), *iv).encrypt(&mut self.buf)?;
+                self.inner.write_all(&self.buf)?;
+
+                *iv = to_block(&self.buf[full - BLOCK_SIZE..]);
+                self.pending.clear();
+                self.pending.extend_from_slice(&data[take..]);
+            }
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+
+    fn cipher() -> RjindaelCipher {
+        RjindaelCipher::new(*b"streaming key 01")
+    }
+
+    fn write_in_pieces(mode: stream::StreamMode, msg: &[u8]) -> Vec<u8> {
+        let mut writer: _ = EncryptWriter::new(Vec::new(), cipher(), [0x24; 16], mode);
+        let mut rest: _ = msg;
+        for len in [1, 7, 15, 16, 33].iter().cycle() {
+            if rest.is_empty() {
+                break
+            }
+            let (head, tail): _ = rest.split_at((*len).min(rest.len()));
+            writer.write_all(head).unwrap();
+            rest = tail;
+        }
+        writer.finalize().unwrap()
+    }
+
+    #[test]
+    fn test_ctr() {
+        let msg: Vec<u8> = (0..200).collect();
+        let mut exp: _ = msg.clone();
+        modes::Ctr::new(cipher(), [0x24; 16]).apply_keystream(&mut exp);
+        assert_eq!(exp, write_in_pieces(stream::StreamMode::Ctr, &msg));
+    }
+
+    #[test]
+    fn test_cbc() {
+        for len in [0, 15, 16, 17, 200].iter() {
+            let msg: Vec<u8> = (0..*len as u8).collect();
+            let mut exp: _ = msg.clone();
+            stream::pad(&mut exp);
+            modes::Cbc::new(cipher(), [0x24; 16]).encrypt(&mut exp).unwrap();
+            assert_eq!(exp, write_in_pieces(stream::StreamMode::Cbc, &msg));
+        }
+    }
+
+    // fails the first write, then passes everything through.
+    struct Flaky {
+        failed: bool,
+        out: Vec<u8>,
+    }
+
+    impl io::Write for Flaky {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            if !self.failed {
+                self.failed = true;
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, "try again"))
+            }
+            self.out.extend_from_slice(data);
+            Ok(data.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_retry_after_error() {
+        // a failed write leaves the stream where it was, so repeating it loses nothing.
+        let msg: Vec<u8> = (0..40).collect();
+        for mode in [stream::StreamMode::Ctr, stream::StreamMode::Cbc].iter() {
+            let flaky: _ = Flaky { failed: false, out: Vec::new() };
+            let mut writer: _ = EncryptWriter::new(flaky, cipher(), [0x24; 16], *mode);
+            assert_eq!(io::ErrorKind::WouldBlock, writer.write(&msg[..20]).unwrap_err().kind());
+            writer.write_all(&msg[..20]).unwrap();
+            writer.write_all(&msg[20..]).unwrap();
+            assert_eq!(write_in_pieces(*mode, &msg), writer.finalize().unwrap().out);
+        }
+    }
+
+    #[test]
+    fn test_held_back() {
+        let mut writer: _ = EncryptWriter::new(Vec::new(), cipher(), [0; 16], stream::StreamMode::Cbc);
+        writer.write_all(&[0; 20]).unwrap();
+        assert_eq!(16, writer.get_ref().len());
+        writer.write_all(&[0; 12]).unwrap();
+        assert_eq!(32, writer.get_ref().len());
+        assert_eq!(48, writer.finalize().unwrap().len());
+    }
+}